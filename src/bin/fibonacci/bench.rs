@@ -0,0 +1,152 @@
+use std::io::{self, Write};
+use std::mem::size_of;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use crate::biguint::BigUint;
+use crate::recurrence::Recurrence;
+use crate::{fibonacci_big, fibonacci_fast_doubling, fibonacci_plain, fibonacci_with_cache};
+use crate::{MAX_U128_FIBONACCI_N, MAX_U32_FIBONACCI_N};
+
+// fibonacci_plain takes exponential time, beyond this a single run takes seconds
+const BENCH_MAX_PLAIN_N: usize = 35;
+
+// rough size of one stack frame of the recursive strategies
+const ESTIMATED_FRAME_BYTES: usize = 64;
+
+struct BenchStrategy {
+    name: &'static str,
+    max_n: usize,
+    run: fn(usize) -> BigUint,
+    // estimated peak memory in bytes, given n and the result
+    memory: fn(usize, &BigUint) -> usize,
+}
+
+fn big_bytes(value: &BigUint) -> usize {
+    value.bits().div_ceil(64) as usize * size_of::<u64>()
+}
+
+const STRATEGIES: [BenchStrategy; 5] = [
+    BenchStrategy {
+        name: "plain",
+        max_n: if BENCH_MAX_PLAIN_N < MAX_U32_FIBONACCI_N { BENCH_MAX_PLAIN_N } else { MAX_U32_FIBONACCI_N },
+        run: |n| BigUint::from(fibonacci_plain(n as u32) as u64),
+        memory: |n, _| n * ESTIMATED_FRAME_BYTES,
+    },
+    BenchStrategy {
+        name: "cached",
+        max_n: MAX_U128_FIBONACCI_N,
+        run: |n| BigUint::from(fibonacci_with_cache(n).expect("n is within the u128 range")),
+        // n hash map entries plus n recursion frames
+        memory: |n, _| n * (size_of::<usize>() + size_of::<u128>() + ESTIMATED_FRAME_BYTES),
+    },
+    BenchStrategy {
+        name: "iterative",
+        max_n: usize::MAX,
+        run: fibonacci_big,
+        memory: |_, result| 3 * big_bytes(result),
+    },
+    BenchStrategy {
+        name: "doubling",
+        max_n: usize::MAX,
+        run: fibonacci_fast_doubling,
+        // three state values and four products of about the size of the result
+        memory: |_, result| 7 * big_bytes(result),
+    },
+    BenchStrategy {
+        name: "recurrence",
+        max_n: usize::MAX,
+        run: |n| Recurrence::fibonacci().term(n),
+        // the u128 cache up to the overflow plus a window of two big values
+        memory: |n, result| {
+            n.min(MAX_U128_FIBONACCI_N + 1) * (size_of::<usize>() + size_of::<u128>()) + 2 * big_bytes(result)
+        },
+    },
+];
+
+fn human_bytes(bytes: usize) -> String {
+    match bytes {
+        b if b < 1 << 10 => format!("{} B", b),
+        b if b < 1 << 20 => format!("{:.1} KiB", b as f64 / (1 << 10) as f64),
+        b => format!("{:.1} MiB", b as f64 / (1 << 20) as f64),
+    }
+}
+
+/// Runs every strategy for each n in the range (with the given step) and prints a table of
+/// timings and memory estimates. Returns the values of n for which the strategies disagreed.
+pub fn run_bench(out: &mut impl Write, range: Range<usize>, step: usize) -> io::Result<Vec<usize>> {
+    let mut mismatches: Vec<usize> = Vec::new();
+    writeln!(out, "{:>10}  {:<10}  {:>12}  {:>14}  result", "n", "strategy", "time", "memory (est.)")?;
+    for n in range.step_by(step) {
+        let mut reference: Option<BigUint> = None;
+        for strategy in STRATEGIES.iter() {
+            if n > strategy.max_n {
+                writeln!(out, "{:>10}  {:<10}  {:>12}  {:>14}  skipped, n > {}", n, strategy.name, "-", "-", strategy.max_n)?;
+                continue;
+            }
+
+            let start = Instant::now();
+            let result = (strategy.run)(n);
+            let elapsed: Duration = start.elapsed();
+
+            let verdict = match &reference {
+                None => "ok",
+                Some(expected) if *expected == result => "ok",
+                Some(_) => "MISMATCH",
+            };
+            if verdict != "ok" && mismatches.last() != Some(&n) {
+                mismatches.push(n);
+            }
+            writeln!(out, "{:>10}  {:<10}  {:>12}  {:>14}  {}",
+                     n, strategy.name, format!("{:.2?}", elapsed), human_bytes((strategy.memory)(n, &result)), verdict)?;
+            if reference.is_none() {
+                reference = Some(result);
+            }
+        }
+    }
+    out.flush()?;
+    Ok(mismatches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bench(range: Range<usize>, step: usize) -> (Vec<usize>, Vec<String>) {
+        let mut out: Vec<u8> = Vec::new();
+        let mismatches = run_bench(&mut out, range, step).unwrap();
+        let lines = String::from_utf8(out).unwrap().lines().map(String::from).collect();
+        (mismatches, lines)
+    }
+
+    #[test]
+    fn strategies_agree() {
+        let (mismatches, lines) = bench(30..38, 3);
+        assert_eq!(mismatches, Vec::<usize>::new());
+        assert!(lines[0].trim_start().starts_with("n  strategy"), "{}", lines[0]);
+        assert!(lines[0].ends_with("memory (est.)  result"), "{}", lines[0]);
+        // one row per strategy for each of 30, 33 and 36
+        assert_eq!(lines.len(), 1 + 3 * STRATEGIES.len());
+        let skipped = format!("{:>10}  {:<10}", 36, "plain");
+        for row in &lines[1..] {
+            if row.starts_with(&skipped) {
+                assert!(row.ends_with(&format!("skipped, n > {}", BENCH_MAX_PLAIN_N)), "{}", row);
+            } else {
+                assert!(row.ends_with("  ok"), "{}", row);
+            }
+        }
+    }
+
+    #[test]
+    fn skips_strategies_beyond_their_limit() {
+        let (mismatches, lines) = bench(MAX_U128_FIBONACCI_N..MAX_U128_FIBONACCI_N + 2, 1);
+        assert_eq!(mismatches, Vec::<usize>::new());
+        assert_eq!(lines.len(), 1 + 2 * STRATEGIES.len());
+        let skipped: Vec<String> = lines[1..].iter()
+            .filter(|row| row.contains("skipped"))
+            .map(|row| row.split_whitespace().take(2).collect::<Vec<&str>>().join(" "))
+            .collect();
+        assert_eq!(skipped, ["186 plain", "187 plain", "187 cached"]);
+        assert!(lines.iter().any(|row| row.ends_with("skipped, n > 186")));
+    }
+}
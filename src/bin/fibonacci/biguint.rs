@@ -0,0 +1,287 @@
+use std::fmt;
+use std::ops::{Add, Mul};
+use std::str::FromStr;
+
+// largest power of ten that fits into a single limb; used for decimal conversion
+const DECIMAL_CHUNK: u64 = 10_000_000_000_000_000_000;
+const DECIMAL_CHUNK_DIGITS: usize = 19;
+
+/// Arbitrary-precision unsigned integer.
+///
+/// The value is stored as little-endian base 2^64 limbs without trailing zero limbs,
+/// so zero is represented by an empty limb vector.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BigUint {
+    limbs: Vec<u64>,
+}
+
+impl BigUint {
+    pub fn zero() -> BigUint {
+        BigUint { limbs: Vec::new() }
+    }
+
+    pub fn one() -> BigUint {
+        BigUint { limbs: vec![1] }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    /// Returns the value as `u128`, or `None` if it does not fit.
+    pub fn to_u128(&self) -> Option<u128> {
+        match self.limbs.len() {
+            0 => Some(0),
+            1 => Some(self.limbs[0] as u128),
+            2 => Some(((self.limbs[1] as u128) << 64) | self.limbs[0] as u128),
+            _ => None,
+        }
+    }
+
+    /// Number of significant bits, 0 for zero.
+    pub fn bits(&self) -> u64 {
+        match self.limbs.last() {
+            Some(&top) => self.limbs.len() as u64 * 64 - top.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    /// Returns whether bit `i` (counted from the least significant bit) is set.
+    pub fn bit(&self, i: u64) -> bool {
+        match self.limbs.get((i / 64) as usize) {
+            Some(&limb) => (limb >> (i % 64)) & 1 == 1,
+            None => false,
+        }
+    }
+
+    fn normalize(&mut self) {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+    }
+
+    /// Divides `self` in place by a single limb and returns the remainder.
+    fn div_rem_small(&mut self, divisor: u64) -> u64 {
+        let mut remainder: u128 = 0;
+        for limb in self.limbs.iter_mut().rev() {
+            let current = (remainder << 64) | *limb as u128;
+            *limb = (current / divisor as u128) as u64;
+            remainder = current % divisor as u128;
+        }
+        self.normalize();
+        remainder as u64
+    }
+
+    /// Computes `self * factor + summand` in place.
+    fn mul_add_small(&mut self, factor: u64, summand: u64) {
+        let mut carry = summand as u128;
+        for limb in self.limbs.iter_mut() {
+            let current = *limb as u128 * factor as u128 + carry;
+            *limb = current as u64;
+            carry = current >> 64;
+        }
+        if carry > 0 {
+            self.limbs.push(carry as u64);
+        }
+        self.normalize();
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseBigUintError;
+
+impl fmt::Display for ParseBigUintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid digit found in string")
+    }
+}
+
+impl FromStr for BigUint {
+    type Err = ParseBigUintError;
+
+    /// Parses a non-empty string of decimal digits.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ParseBigUintError);
+        }
+
+        let mut result = BigUint::zero();
+        for chunk in s.as_bytes().chunks(DECIMAL_CHUNK_DIGITS) {
+            // a chunk holds only ascii digits, so it is valid utf-8 and fits into a u64
+            let value: u64 = std::str::from_utf8(chunk).unwrap().parse().unwrap();
+            result.mul_add_small(10u64.pow(chunk.len() as u32), value);
+        }
+        Ok(result)
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(value: u64) -> Self {
+        let mut result = BigUint { limbs: vec![value] };
+        result.normalize();
+        result
+    }
+}
+
+impl From<u128> for BigUint {
+    fn from(value: u128) -> Self {
+        let mut result = BigUint { limbs: vec![value as u64, (value >> 64) as u64] };
+        result.normalize();
+        result
+    }
+}
+
+impl Add for &BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: &BigUint) -> BigUint {
+        let (long, short) = if self.limbs.len() >= other.limbs.len() {
+            (&self.limbs, &other.limbs)
+        } else {
+            (&other.limbs, &self.limbs)
+        };
+
+        let mut limbs: Vec<u64> = Vec::with_capacity(long.len() + 1);
+        let mut carry = false;
+        for (i, &a) in long.iter().enumerate() {
+            let b = short.get(i).copied().unwrap_or(0);
+            let (sum, c1) = a.overflowing_add(b);
+            let (sum, c2) = sum.overflowing_add(carry as u64);
+            limbs.push(sum);
+            carry = c1 || c2;
+        }
+        if carry {
+            limbs.push(1);
+        }
+        BigUint { limbs }
+    }
+}
+
+impl Mul for &BigUint {
+    type Output = BigUint;
+
+    // schoolbook multiplication, O(len(a) * len(b))
+    fn mul(self, other: &BigUint) -> BigUint {
+        if self.is_zero() || other.is_zero() {
+            return BigUint::zero();
+        }
+
+        let mut limbs: Vec<u64> = vec![0; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry: u128 = 0;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let current = limbs[i + j] as u128 + a as u128 * b as u128 + carry;
+                limbs[i + j] = current as u64;
+                carry = current >> 64;
+            }
+            limbs[i + other.limbs.len()] = carry as u64;
+        }
+
+        let mut result = BigUint { limbs };
+        result.normalize();
+        result
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return f.pad_integral(true, "", "0");
+        }
+
+        // collect base 10^19 chunks, least significant first
+        let mut rest = self.clone();
+        let mut chunks: Vec<u64> = Vec::new();
+        while !rest.is_zero() {
+            chunks.push(rest.div_rem_small(DECIMAL_CHUNK));
+        }
+
+        let mut digits = String::with_capacity(chunks.len() * DECIMAL_CHUNK_DIGITS);
+        let mut chunks = chunks.iter().rev();
+        if let Some(most_significant) = chunks.next() {
+            digits.push_str(&most_significant.to_string());
+        }
+        for chunk in chunks {
+            digits.push_str(&format!("{:0width$}", chunk, width = DECIMAL_CHUNK_DIGITS));
+        }
+        f.pad_integral(true, "", &digits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(s: &str) -> BigUint {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn add_carries_across_limbs() {
+        let max = BigUint::from(u64::MAX);
+        assert_eq!(&max + &BigUint::one(), BigUint::from(1u128 << 64));
+        assert_eq!(&BigUint::from(u128::MAX) + &BigUint::one(), big("340282366920938463463374607431768211456"));
+        assert_eq!(&BigUint::one() + &BigUint::from(u128::MAX), big("340282366920938463463374607431768211456"));
+        assert_eq!(&BigUint::zero() + &max, max);
+    }
+
+    #[test]
+    fn mul_over_several_limbs() {
+        let max = BigUint::from(u64::MAX);
+        assert_eq!(&max * &max, big("340282366920938463426481119284349108225"));
+        let max_128 = BigUint::from(u128::MAX);
+        assert_eq!(&max_128 * &max_128,
+                   big("115792089237316195423570985008687907852589419931798687112530834793049593217025"));
+        assert_eq!(&max_128 * &BigUint::zero(), BigUint::zero());
+        assert_eq!(&BigUint::one() * &max_128, max_128);
+    }
+
+    #[test]
+    fn display_pads_inner_chunks_with_zeros() {
+        assert_eq!(BigUint::zero().to_string(), "0");
+        assert_eq!(BigUint::from(10_000_000_000_000_000_000u64).to_string(), "10000000000000000000");
+        assert_eq!(BigUint::from(10u128.pow(38)).to_string(), format!("1{}", "0".repeat(38)));
+        assert_eq!(BigUint::from(1u128 << 64).to_string(), "18446744073709551616");
+        assert_eq!((&BigUint::from(u128::MAX) + &BigUint::one()).to_string(), "340282366920938463463374607431768211456");
+        assert_eq!(format!("{:>5}", BigUint::from(42u64)), "   42");
+    }
+
+    #[test]
+    fn parses_what_it_displays() {
+        for s in ["0", "1", "18446744073709551615", "18446744073709551616", "10000000000000000000",
+                  "100000000000000000000000000000000000000", "123456789012345678901234567890123456789012345678901234567890"] {
+            assert_eq!(big(s).to_string(), s);
+        }
+        assert_eq!(big("007"), BigUint::from(7u64));
+        for invalid in ["", "-1", "1a", "+1", " 1", "1.0"] {
+            assert_eq!(invalid.parse::<BigUint>(), Err(ParseBigUintError), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(BigUint::from(0u64), BigUint::zero());
+        assert_eq!(BigUint::from(0u128), BigUint::zero());
+        assert_eq!(BigUint::from(u128::MAX).to_u128(), Some(u128::MAX));
+        assert_eq!((&BigUint::from(u128::MAX) + &BigUint::one()).to_u128(), None);
+        assert_eq!(BigUint::from(1u128 << 64).bits(), 65);
+        assert!(BigUint::from(1u128 << 64).bit(64));
+        assert!(!BigUint::from(1u128 << 64).bit(63));
+        assert_eq!(BigUint::zero().bits(), 0);
+    }
+
+    #[test]
+    fn fibonacci_numbers_beyond_u128() {
+        let (mut current, mut next) = (BigUint::zero(), BigUint::one());
+        let mut fibonacci: Vec<BigUint> = Vec::new();
+        for _ in 0..=1000 {
+            fibonacci.push(current.clone());
+            (current, next) = (next.clone(), &current + &next);
+        }
+        assert_eq!(fibonacci[186].to_u128(), Some(332_825_110_087_067_562_321_196_029_789_634_457_848));
+        assert_eq!(fibonacci[187].to_u128(), None);
+        assert_eq!(fibonacci[187].to_string(), "538522340430300790495419781092981030533");
+        assert_eq!(fibonacci[1000].to_string(),
+                   "43466557686937456435688527675040625802564660517371780402481729089536555417949051890403879840079255169295922593080322634775209689623239873322471161642996440906533187938298969649928516003704476137795166849228875");
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::PathBuf;
+use std::process;
+
+// File layout, all integers little endian:
+//
+//   magic     4 bytes   "FIBC"
+//   version   u32       FORMAT_VERSION
+//   count     u64       number of entries
+//   entries   count * (n: u64, F(n): u128)
+//   checksum  u64       FNV-1a over all preceding bytes
+const MAGIC: &[u8; 4] = b"FIBC";
+const FORMAT_VERSION: u32 = 1;
+const HEADER_LEN: usize = 4 + 4 + 8;
+const ENTRY_LEN: usize = 8 + 16;
+const CHECKSUM_LEN: usize = 8;
+
+/// Environment variable to override the location of the cache file.
+pub const CACHE_FILE_VARIABLE: &str = "FIBONACCI_CACHE_FILE";
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+// $XDG_CACHE_HOME, which has to be absolute to count, or else $HOME/.cache
+fn user_cache_directory() -> Option<PathBuf> {
+    match env::var_os("XDG_CACHE_HOME").map(PathBuf::from) {
+        Some(directory) if directory.is_absolute() => Some(directory),
+        _ => env::var_os("HOME")
+            .filter(|home| !home.is_empty())
+            .map(|home| PathBuf::from(home).join(".cache")),
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+/// File-backed copy of the `fibonacci_cached` memoization table, so repeated runs can start
+/// from what earlier runs already computed.
+pub struct DiskCache {
+    path: PathBuf,
+}
+
+impl DiskCache {
+    pub fn new(path: PathBuf) -> DiskCache {
+        DiskCache { path }
+    }
+
+    /// Uses `$FIBONACCI_CACHE_FILE` if set, `fibonacci/cache.bin` in the user's cache directory otherwise.
+    /// `None` if neither is known.
+    pub fn at_default_location() -> Option<DiskCache> {
+        match env::var_os(CACHE_FILE_VARIABLE) {
+            Some(path) => Some(DiskCache::new(PathBuf::from(path))),
+            None => user_cache_directory().map(|directory| DiskCache::new(directory.join("fibonacci").join("cache.bin"))),
+        }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Loads the cache; a missing file is an empty cache.
+    pub fn load(&self) -> io::Result<HashMap<usize, u128>> {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(e) => return Err(e),
+        };
+        decode(&bytes)
+    }
+
+    /// Writes the cache to a temporary file first and renames it, so readers never see half a file.
+    /// The temporary file is created fresh, never opened through an existing file or link.
+    pub fn save(&self, cache: &HashMap<usize, u128>) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(format!(".{}.tmp", process::id()));
+        let written = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temporary)
+            .and_then(|mut file| file.write_all(&encode(cache)))
+            .and_then(|_| fs::rename(&temporary, &self.path));
+        if written.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+        written
+    }
+
+    pub fn clear(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
+fn encode(cache: &HashMap<usize, u128>) -> Vec<u8> {
+    let mut entries: Vec<(&usize, &u128)> = cache.iter().collect();
+    entries.sort();
+
+    let mut bytes: Vec<u8> = Vec::with_capacity(HEADER_LEN + entries.len() * ENTRY_LEN + CHECKSUM_LEN);
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+    for (&n, &value) in entries {
+        bytes.extend_from_slice(&(n as u64).to_le_bytes());
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    let checksum = fnv1a(&bytes);
+    bytes.extend_from_slice(&checksum.to_le_bytes());
+    bytes
+}
+
+fn decode(bytes: &[u8]) -> io::Result<HashMap<usize, u128>> {
+    if bytes.len() < HEADER_LEN + CHECKSUM_LEN || &bytes[0..4] != MAGIC {
+        return Err(invalid_data("not a fibonacci cache file"));
+    }
+    let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+    if version != FORMAT_VERSION {
+        return Err(invalid_data(&format!("unsupported cache format version {}", version)));
+    }
+    let (content, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+    if fnv1a(content) != u64::from_le_bytes(checksum.try_into().unwrap()) {
+        return Err(invalid_data("cache file checksum mismatch"));
+    }
+    let count = u64::from_le_bytes(content[8..16].try_into().unwrap());
+    let entries = &content[HEADER_LEN..];
+    if entries.len() as u64 != count.saturating_mul(ENTRY_LEN as u64) {
+        return Err(invalid_data("cache file length does not match its entry count"));
+    }
+
+    let mut cache: HashMap<usize, u128> = HashMap::with_capacity(count as usize);
+    for entry in entries.chunks_exact(ENTRY_LEN) {
+        let n = u64::from_le_bytes(entry[0..8].try_into().unwrap());
+        let value = u128::from_le_bytes(entry[8..].try_into().unwrap());
+        let n = usize::try_from(n).map_err(|_| invalid_data("cache entry index does not fit into usize"))?;
+        cache.insert(n, value);
+    }
+
+    // every entry has to follow from F(1) = F(2) = 1, or from F(0) = 0
+    for (&n, &value) in cache.iter() {
+        let expected = match n {
+            0 => Some(0),
+            1 | 2 => Some(1),
+            _ => match (cache.get(&(n - 1)), cache.get(&(n - 2))) {
+                (Some(&previous), Some(&before_previous)) => previous.checked_add(before_previous),
+                _ => None,
+            },
+        };
+        if expected != Some(value) {
+            return Err(invalid_data(&format!("cache entry for n = {} is not F(n-1) + F(n-2)", n)));
+        }
+    }
+    Ok(cache)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // F(1) to F(n), as fibonacci_cached leaves them
+    fn cache_up_to(n: usize) -> HashMap<usize, u128> {
+        let mut cache: HashMap<usize, u128> = HashMap::new();
+        let (mut current, mut next) = (1u128, 1u128);
+        for i in 1..=n {
+            cache.insert(i, current);
+            (current, next) = (next, current.saturating_add(next));
+        }
+        cache
+    }
+
+    // re-encodes the content with a valid checksum
+    fn with_checksum(mut content: Vec<u8>) -> Vec<u8> {
+        let checksum = fnv1a(&content);
+        content.extend_from_slice(&checksum.to_le_bytes());
+        content
+    }
+
+    #[test]
+    fn round_trip() {
+        for cache in [HashMap::new(), cache_up_to(2), cache_up_to(186)] {
+            assert_eq!(decode(&encode(&cache)).unwrap(), cache);
+        }
+        let mut with_zero = cache_up_to(10);
+        with_zero.insert(0, 0);
+        assert_eq!(decode(&encode(&with_zero)).unwrap(), with_zero);
+    }
+
+    #[test]
+    fn rejects_checksum_mismatch() {
+        let mut bytes = encode(&cache_up_to(50));
+        bytes[HEADER_LEN + 10] ^= 1;
+        assert_eq!(decode(&bytes).unwrap_err().to_string(), "cache file checksum mismatch");
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let bytes = encode(&cache_up_to(50));
+        for length in [0, 3, HEADER_LEN, bytes.len() - 1, bytes.len() - ENTRY_LEN] {
+            assert!(decode(&bytes[..length]).is_err(), "length {}", length);
+        }
+        // a consistent checksum does not make up for missing entries
+        let content = bytes[..bytes.len() - CHECKSUM_LEN - ENTRY_LEN].to_vec();
+        assert_eq!(decode(&with_checksum(content)).unwrap_err().to_string(),
+                   "cache file length does not match its entry count");
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut content = encode(&cache_up_to(5));
+        content.truncate(content.len() - CHECKSUM_LEN);
+        content[4..8].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert_eq!(decode(&with_checksum(content)).unwrap_err().to_string(), "unsupported cache format version 2");
+        assert_eq!(decode(b"JUNKJUNKJUNKJUNKJUNKJUNK").unwrap_err().to_string(), "not a fibonacci cache file");
+    }
+
+    #[test]
+    fn rejects_entries_breaking_the_recurrence() {
+        let mut wrong = cache_up_to(20);
+        wrong.insert(17, 1_598);
+        assert!(decode(&encode(&wrong)).is_err());
+
+        let mut gap = cache_up_to(20);
+        gap.remove(&10);
+        assert!(decode(&encode(&gap)).is_err());
+
+        // F(187) does not fit, whatever value the file claims
+        let mut overflow = cache_up_to(186);
+        overflow.insert(187, u128::MAX);
+        assert!(decode(&encode(&overflow)).is_err());
+    }
+}
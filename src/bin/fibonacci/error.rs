@@ -0,0 +1,125 @@
+use std::fmt;
+use std::num::{IntErrorKind, ParseIntError};
+use std::str::FromStr;
+
+use crate::biguint::BigUint;
+
+/// Everything that can go wrong on the command line, each kind with its own exit code
+/// so scripts can tell the failures apart.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FibonacciError {
+    /// the arguments do not match any known command
+    Usage,
+    InvalidNumber(String),
+    Negative(String),
+    Overflow { value: String, target: &'static str },
+    TooLargeForMode { n: usize, mode: &'static str, max: usize },
+    InvalidArgument(String),
+    Io(String),
+    /// strategies computed different values for these n
+    Mismatch(Vec<usize>),
+}
+
+impl FibonacciError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            FibonacciError::Usage => 1,
+            FibonacciError::InvalidNumber(_) => 2,
+            FibonacciError::Negative(_) => 3,
+            FibonacciError::Overflow { .. } => 4,
+            FibonacciError::TooLargeForMode { .. } => 5,
+            FibonacciError::InvalidArgument(_) => 6,
+            FibonacciError::Io(_) => 7,
+            FibonacciError::Mismatch(_) => 8,
+        }
+    }
+}
+
+impl fmt::Display for FibonacciError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FibonacciError::Usage => write!(f, "invalid arguments"),
+            FibonacciError::InvalidNumber(input) => {
+                write!(f, "'{}' is not a valid number, please provide a non-negative integer", input)
+            }
+            FibonacciError::Negative(input) => {
+                write!(f, "'{}' is negative, please provide a non-negative integer", input)
+            }
+            FibonacciError::Overflow { value, target } => write!(f, "{} does not fit into {}", value, target),
+            FibonacciError::TooLargeForMode { n, mode, max } => {
+                write!(f, "{} is too large for {}, the maximum is {}", n, mode, max)
+            }
+            FibonacciError::InvalidArgument(message) => write!(f, "{}", message),
+            FibonacciError::Io(message) => write!(f, "i/o error: {}", message),
+            FibonacciError::Mismatch(ns) => write!(f, "strategies disagree for n = {:?}", ns),
+        }
+    }
+}
+
+fn looks_negative(input: &str) -> bool {
+    match input.trim().strip_prefix('-') {
+        Some(digits) => !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()),
+        None => false,
+    }
+}
+
+fn invalid_or_negative(input: &str) -> FibonacciError {
+    if looks_negative(input) {
+        FibonacciError::Negative(input.to_string())
+    } else {
+        FibonacciError::InvalidNumber(input.to_string())
+    }
+}
+
+/// Parses a non-negative integer, telling malformed, negative and too large input apart.
+pub fn parse_integer<T>(input: &str, target: &'static str) -> Result<T, FibonacciError>
+    where T: FromStr<Err=ParseIntError>
+{
+    input.parse::<T>().map_err(|e| match e.kind() {
+        IntErrorKind::PosOverflow => FibonacciError::Overflow { value: format!("'{}'", input), target },
+        _ => invalid_or_negative(input),
+    })
+}
+
+pub fn parse_biguint(input: &str) -> Result<BigUint, FibonacciError> {
+    input.parse::<BigUint>().map_err(|_| invalid_or_negative(input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_malformed_negative_and_overflowing_input_apart() {
+        assert_eq!(parse_integer::<usize>("42", "usize"), Ok(42));
+        assert_eq!(parse_integer::<usize>("-5", "usize"), Err(FibonacciError::Negative(String::from("-5"))));
+        assert_eq!(parse_integer::<usize>("abc", "usize"), Err(FibonacciError::InvalidNumber(String::from("abc"))));
+        assert_eq!(parse_integer::<usize>("-", "usize"), Err(FibonacciError::InvalidNumber(String::from("-"))));
+        assert_eq!(parse_integer::<usize>("", "usize"), Err(FibonacciError::InvalidNumber(String::new())));
+        let too_large = (usize::MAX as u128 + 1).to_string();
+        assert_eq!(parse_integer::<usize>(&too_large, "usize"),
+                   Err(FibonacciError::Overflow { value: format!("'{}'", too_large), target: "usize" }));
+        assert_eq!(parse_biguint(&too_large).map(|n| n.to_string()), Ok(too_large));
+        assert_eq!(parse_biguint("-5"), Err(FibonacciError::Negative(String::from("-5"))));
+        assert_eq!(parse_biguint("1a"), Err(FibonacciError::InvalidNumber(String::from("1a"))));
+    }
+
+    #[test]
+    fn exit_codes() {
+        assert_eq!(FibonacciError::Usage.exit_code(), 1);
+        assert_eq!(parse_integer::<usize>("abc", "usize").unwrap_err().exit_code(), 2);
+        assert_eq!(parse_integer::<usize>("-5", "usize").unwrap_err().exit_code(), 3);
+        assert_eq!(parse_integer::<u32>("4294967296", "u32").unwrap_err().exit_code(), 4);
+        assert_eq!(FibonacciError::TooLargeForMode { n: 50, mode: "the plain strategy", max: 47 }.exit_code(), 5);
+        assert_eq!(FibonacciError::InvalidArgument(String::new()).exit_code(), 6);
+        assert_eq!(FibonacciError::Io(String::new()).exit_code(), 7);
+        assert_eq!(FibonacciError::Mismatch(vec![3]).exit_code(), 8);
+    }
+
+    #[test]
+    fn messages() {
+        assert_eq!(parse_integer::<usize>("-5", "usize").unwrap_err().to_string(),
+                   "'-5' is negative, please provide a non-negative integer");
+        assert_eq!(parse_integer::<u32>("4294967296", "u32").unwrap_err().to_string(), "'4294967296' does not fit into u32");
+    }
+}
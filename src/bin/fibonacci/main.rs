@@ -0,0 +1,372 @@
+use std::collections::HashMap;
+use std::env;
+use std::io::{self, BufWriter, Read, Write};
+use std::process::exit;
+
+use biguint::BigUint;
+use disk_cache::DiskCache;
+use error::FibonacciError;
+use recurrence::Recurrence;
+use stream::Format;
+use zeckendorf::FibonacciCodec;
+
+mod bench;
+mod biguint;
+mod disk_cache;
+mod error;
+mod modular;
+mod recurrence;
+mod stream;
+mod zeckendorf;
+
+// F(47) is the largest fibonacci number that fits into a u32
+const MAX_U32_FIBONACCI_N: usize = 47;
+// F(186) is the largest fibonacci number that fits into a u128
+const MAX_U128_FIBONACCI_N: usize = 186;
+
+fn fibonacci_plain(n: u32) -> u32 {
+    match n {
+        0 => 0, // practically useless
+        1 => 1,
+        2 => 1,
+        x => fibonacci_plain(x - 1) + fibonacci_plain(x - 2)
+    }
+}
+
+// returns None if F(n) does not fit into a u128
+fn fibonacci_cached(n: usize, cache: &mut HashMap<usize, u128>) -> Option<u128> {
+    match cache.get(&n) {
+        Some(&value) => Some(value),
+        None => {
+            let result = match n {
+                0 => 0,
+                1 => 1,
+                2 => 1,
+                _ => fibonacci_cached(n - 1, cache)?.checked_add(fibonacci_cached(n - 2, cache)?)?,
+            };
+            cache.insert(n, result);
+            Some(result)
+        }
+    }
+}
+
+fn fibonacci_with_cache(n: usize) -> Result<u128, FibonacciError> {
+    let overflow = FibonacciError::Overflow { value: format!("the {}th fibonacci number", n), target: "u128" };
+    // bail out early instead of recursing n levels deep just to find out
+    if n > MAX_U128_FIBONACCI_N {
+        return Err(overflow);
+    }
+    let mut cache: HashMap<usize, u128> = HashMap::new();
+    fibonacci_cached(n, &mut cache).ok_or(overflow)
+}
+
+// like fibonacci_with_cache, but starts from the cache persisted by earlier runs and extends it.
+// The disk cache is only an optimization, so problems with it are reported but not fatal.
+fn fibonacci_with_disk_cache(n: usize, disk_cache: &DiskCache) -> Result<u128, FibonacciError> {
+    if n > MAX_U128_FIBONACCI_N {
+        return fibonacci_with_cache(n);
+    }
+    let mut cache = disk_cache.load().unwrap_or_else(|e| {
+        eprintln!("warning: ignoring cache file {}: {}", disk_cache.path().display(), e);
+        HashMap::new()
+    });
+    let known_entries = cache.len();
+    let result = fibonacci_cached(n, &mut cache);
+    if cache.len() > known_entries {
+        if let Err(e) = disk_cache.save(&cache) {
+            eprintln!("warning: could not write cache file {}: {}", disk_cache.path().display(), e);
+        }
+    }
+    result.ok_or(FibonacciError::Overflow { value: format!("the {}th fibonacci number", n), target: "u128" })
+}
+
+// iterative variant for results beyond the u128 range
+fn fibonacci_big(n: usize) -> BigUint {
+    let mut a = BigUint::zero();
+    let mut b = BigUint::one();
+    for _ in 0..n {
+        let next = &a + &b;
+        a = b;
+        b = next;
+    }
+    a
+}
+
+// O(log n) fast doubling, walking the bits of n from the most significant one.
+//
+// We keep the triple (F(k-1), F(k), F(k+1)), which is the matrix [[1, 1], [1, 0]]^k,
+// so squaring the matrix doubles k without needing a subtraction:
+//   F(2k-1) = F(k)^2 + F(k-1)^2
+//   F(2k)   = F(k) * (F(k-1) + F(k+1))
+//   F(2k+1) = F(k+1)^2 + F(k)^2
+fn fibonacci_fast_doubling(n: usize) -> BigUint {
+    let mut prev = BigUint::one(); // F(-1)
+    let mut current = BigUint::zero(); // F(0)
+    let mut next = BigUint::one(); // F(1)
+
+    for bit in (0..usize::BITS - n.leading_zeros()).rev() {
+        let prev_squared = &prev * &prev;
+        let current_squared = &current * &current;
+        let next_squared = &next * &next;
+        let doubled = &current * &(&prev + &next);
+        prev = &current_squared + &prev_squared;
+        current = doubled;
+        next = &next_squared + &current_squared;
+
+        if (n >> bit) & 1 == 1 {
+            let advanced = &current + &next;
+            prev = current;
+            current = next;
+            next = advanced;
+        }
+    }
+    current
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Strategy {
+    Plain,
+    Cached,
+    FastDoubling,
+}
+
+impl Strategy {
+    fn parse(name: &str) -> Result<Strategy, FibonacciError> {
+        match name {
+            "plain" => Ok(Strategy::Plain),
+            "cached" => Ok(Strategy::Cached),
+            "doubling" => Ok(Strategy::FastDoubling),
+            _ => Err(FibonacciError::InvalidArgument(format!("unknown strategy '{}'", name))),
+        }
+    }
+}
+
+fn print_nth_fibonacci(n: &str, strategy: Strategy, disk_cache: Option<&DiskCache>) -> Result<(), FibonacciError> {
+    let n = error::parse_integer::<usize>(n, "usize")?;
+    match strategy {
+        Strategy::Plain if n <= MAX_U32_FIBONACCI_N => {
+            let result = fibonacci_plain(n as u32);
+            println!("{}th fibonacci number is: {}", n, result);
+        }
+        Strategy::Plain => {
+            return Err(FibonacciError::TooLargeForMode { n, mode: "the plain strategy", max: MAX_U32_FIBONACCI_N });
+        }
+        Strategy::Cached if n <= MAX_U128_FIBONACCI_N => {
+            let result = match disk_cache {
+                Some(disk_cache) => fibonacci_with_disk_cache(n, disk_cache)?,
+                None => fibonacci_with_cache(n)?,
+            };
+            println!("{}th fibonacci number is: {}", n, result);
+        }
+        Strategy::Cached => {
+            let result = fibonacci_big(n);
+            println!("{}th fibonacci number is: {}", n, result);
+        }
+        Strategy::FastDoubling => {
+            let result = fibonacci_fast_doubling(n);
+            println!("{}th fibonacci number is: {}", n, result);
+        }
+    }
+    Ok(())
+}
+
+// n is taken as a decimal string, so it may be much wider than usize
+fn print_nth_fibonacci_mod(n: &str, m: &str, with_pisano_period: bool) -> Result<(), FibonacciError> {
+    let n = error::parse_biguint(n)?;
+    let m = error::parse_integer::<u64>(m, "u64")?;
+    if m == 0 {
+        return Err(FibonacciError::InvalidArgument(String::from("the modulus must be positive")));
+    }
+    println!("{}th fibonacci number mod {} is: {}", n, m, modular::fibonacci_mod(&n, m));
+    if with_pisano_period {
+        if m > modular::MAX_PISANO_MODULUS {
+            return Err(FibonacciError::TooLargeForMode {
+                n: m as usize, mode: "the pisano period", max: modular::MAX_PISANO_MODULUS as usize,
+            });
+        }
+        println!("pisano period of {} is: {}", m, modular::pisano_period(m));
+    }
+    Ok(())
+}
+
+fn print_nth_term(name: &str, recurrence: &Recurrence, n: &str) -> Result<(), FibonacciError> {
+    let n = error::parse_integer::<usize>(n, "usize")?;
+    println!("{}th {} number is: {}", n, name, recurrence.term(n));
+    Ok(())
+}
+
+fn print_fibonacci_range(range: &str, format: &str) -> Result<(), FibonacciError> {
+    let format = Format::parse(format)
+        .ok_or_else(|| FibonacciError::InvalidArgument(format!("unknown format '{}'", format)))?;
+    let range = stream::parse_range(range)
+        .ok_or_else(|| FibonacciError::InvalidArgument(format!("'{}' is not a range like 0..1000 or 0..=1000", range)))?;
+    let mut out = BufWriter::new(io::stdout().lock());
+    match stream::write_range(&mut out, range, format) {
+        Ok(()) => Ok(()),
+        // the reading end went away, e.g. when piping into `head`
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        Err(e) => Err(FibonacciError::Io(e.to_string())),
+    }
+}
+
+fn print_zeckendorf(n: &str) -> Result<(), FibonacciError> {
+    let n = error::parse_integer::<u64>(n, "u64")?;
+    let parts: Vec<String> = FibonacciCodec::new().zeckendorf(n).iter().map(|f| f.to_string()).collect();
+    if parts.is_empty() {
+        println!("zeckendorf representation of 0 is the empty sum");
+    } else {
+        println!("zeckendorf representation of {} is: {}", n, parts.join(" + "));
+    }
+    Ok(())
+}
+
+// reads whitespace or comma separated values from stdin and writes their packed codewords to stdout
+fn fibonacci_encode_stdin() -> Result<(), FibonacciError> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input).map_err(|e| FibonacciError::Io(e.to_string()))?;
+    let values = input.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|value| !value.is_empty())
+        .map(|value| error::parse_integer::<u64>(value, "u64"))
+        .collect::<Result<Vec<u64>, _>>()?;
+    let encoded = FibonacciCodec::new().encode(&values)
+        .map_err(|e| FibonacciError::InvalidArgument(e.to_string()))?;
+    let mut out = io::stdout().lock();
+    out.write_all(&encoded).and_then(|_| out.flush()).map_err(|e| FibonacciError::Io(e.to_string()))
+}
+
+// reads packed codewords from stdin and prints one value per line
+fn fibonacci_decode_stdin() -> Result<(), FibonacciError> {
+    let mut input: Vec<u8> = Vec::new();
+    io::stdin().read_to_end(&mut input).map_err(|e| FibonacciError::Io(e.to_string()))?;
+    let values = FibonacciCodec::new().decode(&input)
+        .map_err(|e| FibonacciError::InvalidArgument(e.to_string()))?;
+    for value in values {
+        println!("{}", value);
+    }
+    Ok(())
+}
+
+fn run_bench(range: &str, step: &str) -> Result<(), FibonacciError> {
+    let range = stream::parse_range(range)
+        .ok_or_else(|| FibonacciError::InvalidArgument(format!("'{}' is not a range like 0..1000 or 0..=1000", range)))?;
+    let step = error::parse_integer::<usize>(step, "usize")?;
+    if step == 0 {
+        return Err(FibonacciError::InvalidArgument(String::from("the step must be positive")));
+    }
+    let mismatches = bench::run_bench(&mut io::stdout().lock(), range, step)
+        .map_err(|e| FibonacciError::Io(e.to_string()))?;
+    if mismatches.is_empty() {
+        println!("all strategies agree");
+        Ok(())
+    } else {
+        Err(FibonacciError::Mismatch(mismatches))
+    }
+}
+
+fn print_usage(program: &str) {
+    println!("Usage: {} [--strategy plain|cached|doubling] <number>", program);
+    println!("       {} mod <number> <modulus> [--pisano]", program);
+    println!("       {} sequence fibonacci|lucas|tribonacci|pell|padovan <number>", program);
+    println!("       {} sequence custom <coefficients> <seeds> <number>", program);
+    println!("       {} range <start>..<end> [--format plain|csv|json]", program);
+    println!("       {} bench <start>..<end> [--step <number>]", program);
+    println!("       {} zeckendorf <number>", program);
+    println!("       {} encode < values.txt > values.fib", program);
+    println!("       {} decode < values.fib", program);
+    println!("  custom recurrences take comma separated lists, e.g. lucas: custom 1,1 2,1 <number>");
+    println!("  default strategy: doubling");
+    println!();
+    println!("The cached strategy keeps its cache in a file between runs: ${}, or else fibonacci/cache.bin", disk_cache::CACHE_FILE_VARIABLE);
+    println!("in $XDG_CACHE_HOME or $HOME/.cache.");
+    println!("  --no-cache     do not read or write the cache file");
+    println!("  --clear-cache  delete the cache file (before running the command, if any)");
+    println!();
+    println!("Exit codes: 1 usage, 2 invalid number, 3 negative number, 4 overflow,");
+    println!("            5 too large for the chosen mode, 6 invalid argument, 7 i/o error,");
+    println!("            8 strategies disagree (bench)");
+}
+
+fn run(args: &[&str]) -> Result<(), FibonacciError> {
+    // the cache flags may appear anywhere, so they are taken out before matching the commands
+    let disk_cache = DiskCache::at_default_location();
+    let clear_cache = args.contains(&"--clear-cache");
+    if clear_cache {
+        if let Some(disk_cache) = &disk_cache {
+            disk_cache.clear().map_err(|e| FibonacciError::Io(e.to_string()))?;
+        }
+    }
+    let disk_cache = if args.contains(&"--no-cache") { None } else { disk_cache.as_ref() };
+    let args: Vec<&str> = args.iter()
+        .copied()
+        .filter(|&arg| arg != "--no-cache" && arg != "--clear-cache")
+        .collect();
+
+    match args.as_slice() {
+        [_, "encode"] => fibonacci_encode_stdin(),
+        [_, "decode"] => fibonacci_decode_stdin(),
+        // nothing to do besides clearing the cache
+        [_] if clear_cache => Ok(()),
+        [_, number] => print_nth_fibonacci(number, Strategy::FastDoubling, disk_cache),
+        [_, "--strategy", name, number] => print_nth_fibonacci(number, Strategy::parse(name)?, disk_cache),
+        [_, "mod", number, modulus] => print_nth_fibonacci_mod(number, modulus, false),
+        [_, "mod", number, modulus, "--pisano"] => print_nth_fibonacci_mod(number, modulus, true),
+        [_, "range", range] => print_fibonacci_range(range, "plain"),
+        [_, "range", range, "--format", format] => print_fibonacci_range(range, format),
+        [_, "sequence", "custom", coefficients, seeds, number] => {
+            let recurrence = Recurrence::parse(coefficients, seeds).map_err(FibonacciError::InvalidArgument)?;
+            print_nth_term("custom sequence", &recurrence, number)
+        }
+        [_, "sequence", name, number] => {
+            let recurrence = Recurrence::by_name(name)
+                .ok_or_else(|| FibonacciError::InvalidArgument(format!("unknown sequence '{}'", name)))?;
+            print_nth_term(name, &recurrence, number)
+        }
+        [_, "bench", range] => run_bench(range, "1"),
+        [_, "bench", range, "--step", step] => run_bench(range, step),
+        [_, "zeckendorf", number] => print_zeckendorf(number),
+        _ => Err(FibonacciError::Usage),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    if let Err(error) = run(&args) {
+        match error {
+            FibonacciError::Usage => print_usage(args[0]),
+            _ => eprintln!("error: {}", error),
+        }
+        exit(error.exit_code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fast_doubling_agrees_with_the_other_strategies() {
+        let powers_of_two = (0..12).map(|k| 1usize << k);
+        for n in (0..=200).chain([MAX_U128_FIBONACCI_N, MAX_U128_FIBONACCI_N + 1]).chain(powers_of_two) {
+            let expected = fibonacci_big(n);
+            assert_eq!(fibonacci_fast_doubling(n), expected, "n = {}", n);
+            match fibonacci_with_cache(n) {
+                Ok(value) => assert_eq!(BigUint::from(value), expected, "n = {}", n),
+                Err(_) => assert!(n > MAX_U128_FIBONACCI_N, "n = {}", n),
+            }
+        }
+        // the plain strategy takes exponential time, so only its first terms are compared
+        for n in 0..=25 {
+            assert_eq!(BigUint::from(fibonacci_plain(n as u32) as u64), fibonacci_big(n), "n = {}", n);
+        }
+    }
+
+    #[test]
+    fn strategies_at_the_edges() {
+        assert_eq!(fibonacci_fast_doubling(0), BigUint::zero());
+        assert_eq!(fibonacci_fast_doubling(1), BigUint::one());
+        assert_eq!(fibonacci_with_cache(MAX_U128_FIBONACCI_N), Ok(332_825_110_087_067_562_321_196_029_789_634_457_848));
+        assert!(fibonacci_with_cache(MAX_U128_FIBONACCI_N + 1).is_err());
+        assert_eq!(fibonacci_fast_doubling(MAX_U128_FIBONACCI_N + 1).to_string(), "538522340430300790495419781092981030533");
+    }
+}
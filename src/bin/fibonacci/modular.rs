@@ -0,0 +1,172 @@
+use crate::biguint::BigUint;
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+fn add_mod(a: u64, b: u64, m: u64) -> u64 {
+    ((a as u128 + b as u128) % m as u128) as u64
+}
+
+/// F(n) mod m via fast doubling over the bits of `n`, so `n` may be arbitrarily large.
+///
+///   F(2k)   = F(k) * (2 * F(k+1) - F(k))
+///   F(2k+1) = F(k)^2 + F(k+1)^2
+pub fn fibonacci_mod(n: &BigUint, m: u64) -> u64 {
+    fibonacci_pair_mod(n, m).0
+}
+
+// (F(n) mod m, F(n+1) mod m)
+fn fibonacci_pair_mod(n: &BigUint, m: u64) -> (u64, u64) {
+    assert!(m > 0, "modulus must be positive");
+
+    let mut current: u64 = 0; // F(k) mod m
+    let mut next: u64 = 1 % m; // F(k+1) mod m
+
+    for bit in (0..n.bits()).rev() {
+        // 2 * F(k+1) - F(k), kept non-negative by adding m before subtracting
+        let factor = add_mod(add_mod(next, next, m), m - current, m);
+        let doubled = mul_mod(current, factor, m);
+        let doubled_next = add_mod(mul_mod(current, current, m), mul_mod(next, next, m), m);
+
+        if n.bit(bit) {
+            current = doubled_next;
+            next = add_mod(doubled, doubled_next, m);
+        } else {
+            current = doubled;
+            next = doubled_next;
+        }
+    }
+    (current, next)
+}
+
+/// Moduli up to here are factorized by trial division in well below a second.
+/// Also keeps every candidate period, which is at most 4m, within u64.
+pub const MAX_PISANO_MODULUS: u64 = 1_000_000_000_000_000;
+
+// splits m into (prime, exponent) pairs, by trial division
+fn factorize(mut m: u64) -> Vec<(u64, u32)> {
+    let mut factors: Vec<(u64, u32)> = Vec::new();
+    let mut p: u64 = 2;
+    while p <= m / p {
+        if m.is_multiple_of(p) {
+            let mut exponent = 0;
+            while m.is_multiple_of(p) {
+                m /= p;
+                exponent += 1;
+            }
+            factors.push((p, exponent));
+        }
+        p += 1;
+    }
+    if m > 1 {
+        factors.push((m, 1));
+    }
+    factors
+}
+
+// whether the sequence mod m is back at (0, 1) after `length` steps
+fn is_period(length: u64, m: u64) -> bool {
+    fibonacci_pair_mod(&BigUint::from(length), m) == (0, 1 % m)
+}
+
+// the smallest period, given a multiple of it and all primes which may divide the quotient
+fn shrink_period(mut period: u64, primes: &[u64], m: u64) -> u64 {
+    for &q in primes {
+        while period.is_multiple_of(q) && is_period(period / q, m) {
+            period /= q;
+        }
+    }
+    period
+}
+
+// π(p) divides p - 1 for p ≡ ±1 (mod 5) and 2(p + 1) for p ≡ ±2 (mod 5); π(5) = 20
+fn pisano_period_of_prime(p: u64) -> u64 {
+    let multiple = match p % 5 {
+        0 => 20,
+        1 | 4 => p - 1,
+        _ => 2 * (p + 1),
+    };
+    let primes: Vec<u64> = factorize(multiple).into_iter().map(|(q, _)| q).collect();
+    shrink_period(multiple, &primes, p)
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+fn lcm(a: u64, b: u64) -> u64 {
+    a / gcd(a, b) * b
+}
+
+/// Pisano period π(m): the period of the fibonacci sequence taken mod m, for 0 < m <= `MAX_PISANO_MODULUS`.
+///
+/// For coprime a and b, π(a * b) = lcm(π(a), π(b)), so only the prime power factors of m matter.
+/// π(p^k) is π(p) times a power of p, at most p^(k-1); π(p) is found among the divisors of
+/// a multiple known in advance. Either way just O(log m) fibonacci numbers mod m get computed.
+pub fn pisano_period(m: u64) -> u64 {
+    assert!(m > 0, "modulus must be positive");
+    assert!(m <= MAX_PISANO_MODULUS, "modulus {} is beyond {}", m, MAX_PISANO_MODULUS);
+
+    factorize(m).into_iter()
+        .map(|(p, exponent)| {
+            let prime_power = p.pow(exponent);
+            shrink_period(pisano_period_of_prime(p) * (prime_power / p), &[p], prime_power)
+        })
+        .fold(1, lcm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // walks the sequence mod m until the pair (0, 1) comes around again
+    fn pisano_period_by_iteration(m: u64) -> u64 {
+        let (mut current, mut next) = (0, 1 % m);
+        let mut period = 0;
+        loop {
+            (current, next) = (next, (current + next) % m);
+            period += 1;
+            if current == 0 && next == 1 % m {
+                return period;
+            }
+        }
+    }
+
+    #[test]
+    fn fibonacci_mod_agrees_with_iteration() {
+        for m in 1..=60u64 {
+            let (mut current, mut next) = (0, 1 % m);
+            for n in 0..500u64 {
+                assert_eq!(fibonacci_mod(&BigUint::from(n), m), current, "F({}) mod {}", n, m);
+                (current, next) = (next, (current + next) % m);
+            }
+        }
+    }
+
+    #[test]
+    fn fibonacci_mod_of_huge_n() {
+        // F(10^18) mod 10^9, and a large modulus which needs the 128 bit products
+        assert_eq!(fibonacci_mod(&"1000000000000000000".parse().unwrap(), 1_000_000_000), 560_546_875);
+        assert_eq!(fibonacci_mod(&BigUint::from(90u64), u64::MAX), 2_880_067_194_370_816_120);
+    }
+
+    #[test]
+    fn pisano_period_agrees_with_iteration() {
+        for m in 1..=3_000 {
+            assert_eq!(pisano_period(m), pisano_period_by_iteration(m), "m = {}", m);
+        }
+    }
+
+    #[test]
+    fn pisano_period_of_large_moduli() {
+        // π(10^k) = 15 * 10^(k-1) for k >= 3
+        assert_eq!(pisano_period(1_000_000_000), 1_500_000_000);
+        assert_eq!(pisano_period(MAX_PISANO_MODULUS), 1_500_000_000_000_000);
+        // 999999999989 is the largest prime below 10^12 and ≡ 4 (mod 5), so π(p) divides p - 1
+        assert!((999_999_999_988u64).is_multiple_of(pisano_period(999_999_999_989)));
+        assert_eq!(pisano_period(2), 3);
+        assert_eq!(pisano_period(5), 20);
+        assert_eq!(pisano_period(1), 1);
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::VecDeque;
+
+use crate::biguint::BigUint;
+
+/// Linear recurrence with constant, non-negative coefficients:
+///
+///   a(n) = c1 * a(n-1) + c2 * a(n-2) + ... + ck * a(n-k)
+///
+/// with the first k terms given as seeds a(0), ..., a(k-1).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recurrence {
+    coefficients: Vec<u128>,
+    seeds: Vec<u128>,
+}
+
+impl Recurrence {
+    pub fn new(coefficients: Vec<u128>, seeds: Vec<u128>) -> Result<Recurrence, String> {
+        if coefficients.is_empty() {
+            return Err(String::from("a recurrence needs at least one coefficient"));
+        }
+        if coefficients.len() != seeds.len() {
+            return Err(format!("a recurrence with {} coefficients needs {} seeds, got {}",
+                               coefficients.len(), coefficients.len(), seeds.len()));
+        }
+        Ok(Recurrence { coefficients, seeds })
+    }
+
+    pub fn fibonacci() -> Recurrence {
+        Recurrence { coefficients: vec![1, 1], seeds: vec![0, 1] }
+    }
+
+    pub fn lucas() -> Recurrence {
+        Recurrence { coefficients: vec![1, 1], seeds: vec![2, 1] }
+    }
+
+    pub fn tribonacci() -> Recurrence {
+        Recurrence { coefficients: vec![1, 1, 1], seeds: vec![0, 0, 1] }
+    }
+
+    pub fn pell() -> Recurrence {
+        Recurrence { coefficients: vec![2, 1], seeds: vec![0, 1] }
+    }
+
+    pub fn padovan() -> Recurrence {
+        Recurrence { coefficients: vec![0, 1, 1], seeds: vec![1, 1, 1] }
+    }
+
+    pub fn by_name(name: &str) -> Option<Recurrence> {
+        match name {
+            "fibonacci" => Some(Recurrence::fibonacci()),
+            "lucas" => Some(Recurrence::lucas()),
+            "tribonacci" => Some(Recurrence::tribonacci()),
+            "pell" => Some(Recurrence::pell()),
+            "padovan" => Some(Recurrence::padovan()),
+            _ => None,
+        }
+    }
+
+    /// Parses comma separated coefficients and seeds, e.g. `"1,1"` and `"2,1"` for the lucas numbers.
+    pub fn parse(coefficients: &str, seeds: &str) -> Result<Recurrence, String> {
+        Recurrence::new(parse_list(coefficients)?, parse_list(seeds)?)
+    }
+
+    pub fn order(&self) -> usize {
+        self.coefficients.len()
+    }
+
+    // the term following the window (most recent term first), None if it overflows u128
+    fn next_u128(&self, window: &[u128]) -> Option<u128> {
+        let mut sum: u128 = 0;
+        for (&coefficient, &term) in self.coefficients.iter().zip(window.iter()) {
+            sum = sum.checked_add(coefficient.checked_mul(term)?)?;
+        }
+        Some(sum)
+    }
+
+    // continues from the window holding the k terms before a(next_index), most recent first
+    fn continue_big(&self, mut window: VecDeque<BigUint>, next_index: usize, n: usize) -> BigUint {
+        let coefficients: Vec<BigUint> = self.coefficients.iter().map(|&c| BigUint::from(c)).collect();
+        for _ in next_index..=n {
+            let mut next = BigUint::zero();
+            for ((&small, coefficient), term) in self.coefficients.iter().zip(coefficients.iter()).zip(window.iter()) {
+                match small {
+                    0 => {}
+                    // the common case, saves a multiplication
+                    1 => next = &next + term,
+                    _ => next = &next + &(coefficient * term),
+                }
+            }
+            window.pop_back();
+            window.push_front(next);
+        }
+        window.pop_front().unwrap()
+    }
+
+    /// Computes a(n) from a window of the last k terms, staying in u128 while the terms fit
+    /// and carrying on with `BigUint` from the first term which does not.
+    pub fn term(&self, n: usize) -> BigUint {
+        if n < self.order() {
+            return BigUint::from(self.seeds[n]);
+        }
+        // most recent term first, matching the order of the coefficients
+        let mut window: Vec<u128> = self.seeds.iter().rev().copied().collect();
+        for i in self.order()..=n {
+            match self.next_u128(&window) {
+                Some(next) => {
+                    window.rotate_right(1);
+                    window[0] = next;
+                }
+                None => return self.continue_big(window.into_iter().map(BigUint::from).collect(), i, n),
+            }
+        }
+        BigUint::from(window[0])
+    }
+}
+
+fn parse_list(list: &str) -> Result<Vec<u128>, String> {
+    list.split(',')
+        .map(|item| item.trim().parse::<u128>()
+            .map_err(|_| format!("'{}' is not a non-negative integer", item.trim())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a(n) in BigUint all the way from the seeds
+    fn term_big(recurrence: &Recurrence, n: usize) -> BigUint {
+        if n < recurrence.order() {
+            return BigUint::from(recurrence.seeds[n]);
+        }
+        let window: VecDeque<BigUint> = recurrence.seeds.iter().rev().map(|&s| BigUint::from(s)).collect();
+        recurrence.continue_big(window, recurrence.order(), n)
+    }
+
+    fn terms(recurrence: &Recurrence, count: usize) -> Vec<String> {
+        (0..count).map(|n| recurrence.term(n).to_string()).collect()
+    }
+
+    #[test]
+    fn known_sequences() {
+        assert_eq!(terms(&Recurrence::lucas(), 10), ["2", "1", "3", "4", "7", "11", "18", "29", "47", "76"]);
+        assert_eq!(terms(&Recurrence::pell(), 10), ["0", "1", "2", "5", "12", "29", "70", "169", "408", "985"]);
+        assert_eq!(terms(&Recurrence::tribonacci(), 10), ["0", "0", "1", "1", "2", "4", "7", "13", "24", "44"]);
+        assert_eq!(terms(&Recurrence::padovan(), 12), ["1", "1", "1", "2", "2", "3", "4", "5", "7", "9", "12", "16"]);
+        assert_eq!(Recurrence::fibonacci().term(100).to_string(), "354224848179261915075");
+        assert_eq!(Recurrence::lucas().term(100).to_string(), "792070839848372253127");
+    }
+
+    #[test]
+    fn switches_to_biguint_on_overflow() {
+        // F(186) is the last fibonacci number within u128
+        let fibonacci = Recurrence::fibonacci();
+        assert_eq!(fibonacci.term(186).to_u128(), Some(332_825_110_087_067_562_321_196_029_789_634_457_848));
+        assert_eq!(fibonacci.term(187).to_string(), "538522340430300790495419781092981030533");
+        for recurrence in [fibonacci, Recurrence::pell(), Recurrence::tribonacci(), Recurrence::padovan()] {
+            for n in [0, 1, 2, 3, 50, 100, 150, 185, 186, 187, 188, 250, 1_000] {
+                assert_eq!(recurrence.term(n), term_big(&recurrence, n), "{:?}, n = {}", recurrence, n);
+            }
+        }
+        // large coefficients overflow in the multiplication already
+        let steep = Recurrence::new(vec![u128::MAX, 1], vec![1, 2]).unwrap();
+        assert_eq!(steep.term(2).to_string(), "680564733841876926926749214863536422911");
+        assert_eq!(steep.term(5), term_big(&steep, 5));
+    }
+
+    #[test]
+    fn long_constant_sequences_stay_in_u128() {
+        let constant = Recurrence::parse("1", "7").unwrap();
+        assert_eq!(constant.term(20_000_000).to_string(), "7");
+    }
+
+    #[test]
+    fn rejects_malformed_definitions() {
+        assert_eq!(Recurrence::parse("1,1", "0"),
+                   Err(String::from("a recurrence with 2 coefficients needs 2 seeds, got 1")));
+        assert_eq!(Recurrence::new(vec![], vec![]), Err(String::from("a recurrence needs at least one coefficient")));
+        assert_eq!(Recurrence::parse("", ""), Err(String::from("'' is not a non-negative integer")));
+        assert_eq!(Recurrence::parse("1,-1", "0,1"), Err(String::from("'-1' is not a non-negative integer")));
+        assert_eq!(Recurrence::parse("1,1", "0,x"), Err(String::from("'x' is not a non-negative integer")));
+        assert_eq!(Recurrence::parse(" 2 , 1 ", "0,1"), Ok(Recurrence::pell()));
+    }
+}
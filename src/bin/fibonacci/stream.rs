@@ -0,0 +1,138 @@
+use std::io::{self, Write};
+use std::ops::Range;
+
+use crate::biguint::BigUint;
+use crate::fibonacci_fast_doubling;
+
+/// Streams consecutive fibonacci numbers as `(n, F(n))` pairs, one addition per term.
+pub struct FibonacciTerms {
+    index: usize,
+    current: BigUint,
+    next: BigUint,
+}
+
+impl FibonacciTerms {
+    /// Starts the stream at F(start); the first two terms are computed by fast doubling.
+    pub fn starting_at(start: usize) -> FibonacciTerms {
+        FibonacciTerms {
+            index: start,
+            current: fibonacci_fast_doubling(start),
+            next: fibonacci_fast_doubling(start + 1),
+        }
+    }
+}
+
+impl Iterator for FibonacciTerms {
+    type Item = (usize, BigUint);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let advanced = &self.current + &self.next;
+        let current = std::mem::replace(&mut self.current, std::mem::replace(&mut self.next, advanced));
+        let index = self.index;
+        self.index += 1;
+        Some((index, current))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Plain,
+    Csv,
+    Json,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Format> {
+        match name {
+            "plain" => Some(Format::Plain),
+            "csv" => Some(Format::Csv),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Parses `start..end` (end exclusive) or `start..=end` (end inclusive).
+pub fn parse_range(range: &str) -> Option<Range<usize>> {
+    let (start, end) = range.split_once("..")?;
+    let start = start.parse::<usize>().ok()?;
+    let end = match end.strip_prefix('=') {
+        Some(inclusive_end) => inclusive_end.parse::<usize>().ok()?.checked_add(1)?,
+        None => end.parse::<usize>().ok()?,
+    };
+    Some(start..end)
+}
+
+/// Writes F(n) for every n in `range`, term by term, without collecting the sequence.
+pub fn write_range(out: &mut impl Write, range: Range<usize>, format: Format) -> io::Result<()> {
+    // an empty range needs no terms at all, not even F(start), which may be expensive or out of reach
+    let terms = (!range.is_empty())
+        .then(|| FibonacciTerms::starting_at(range.start).take(range.len()))
+        .into_iter()
+        .flatten();
+    match format {
+        Format::Plain => {
+            for (_, value) in terms {
+                writeln!(out, "{}", value)?;
+            }
+        }
+        Format::Csv => {
+            writeln!(out, "n,value")?;
+            for (n, value) in terms {
+                writeln!(out, "{},{}", n, value)?;
+            }
+        }
+        Format::Json => {
+            write!(out, "[")?;
+            for (i, (n, value)) in terms.enumerate() {
+                let separator = if i == 0 { "" } else { "," };
+                write!(out, "{}\n  {{\"n\": {}, \"value\": {}}}", separator, n, value)?;
+            }
+            writeln!(out, "\n]")?;
+        }
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn written(range: Range<usize>, format: Format) -> String {
+        let mut out: Vec<u8> = Vec::new();
+        write_range(&mut out, range, format).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(parse_range("3..7"), Some(3..7));
+        assert_eq!(parse_range("3..=7"), Some(3..8));
+        assert_eq!(parse_range("0..0"), Some(0..0));
+        // reversed ranges are valid, just empty
+        assert_eq!(parse_range("7..3"), Some(Range { start: 7, end: 3 }));
+        assert_eq!(parse_range("7..=3"), Some(Range { start: 7, end: 4 }));
+        for malformed in ["", "..", "3", "3..", "..7", "3...7", "3..=", "a..7", "3..-7", "-3..7", "3.7",
+                          "0..=18446744073709551615"] {
+            assert_eq!(parse_range(malformed), None, "{:?}", malformed);
+        }
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(written(0..5, Format::Plain), "0\n1\n1\n2\n3\n");
+        assert_eq!(written(10..13, Format::Csv), "n,value\n10,55\n11,89\n12,144\n");
+        assert_eq!(written(186..188, Format::Json),
+                   "[\n  {\"n\": 186, \"value\": 332825110087067562321196029789634457848},\n  \
+                    {\"n\": 187, \"value\": 538522340430300790495419781092981030533}\n]\n");
+    }
+
+    #[test]
+    fn empty_ranges() {
+        assert_eq!(written(5..5, Format::Plain), "");
+        assert_eq!(written(Range { start: 7, end: 3 }, Format::Csv), "n,value\n");
+        assert_eq!(written(Range { start: 7, end: 3 }, Format::Json), "[\n]\n");
+        // F(usize::MAX) is never computed
+        assert_eq!(written(usize::MAX..usize::MAX, Format::Plain), "");
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::fibonacci_cached;
+
+// F(93) is the largest fibonacci number that fits into a u64
+const MAX_U64_FIBONACCI_N: usize = 93;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CodingError {
+    /// fibonacci coding only covers positive integers
+    Zero,
+    /// the input ended in the middle of a codeword
+    Truncated,
+    /// a codeword describes a value beyond u64
+    Overflow,
+}
+
+impl fmt::Display for CodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodingError::Zero => write!(f, "fibonacci coding cannot represent 0"),
+            CodingError::Truncated => write!(f, "input ends in the middle of a codeword"),
+            CodingError::Overflow => write!(f, "codeword describes a value that does not fit into u64"),
+        }
+    }
+}
+
+/// Zeckendorf representation and fibonacci universal coding of u64 values.
+///
+/// Every positive integer is a unique sum of non-consecutive fibonacci numbers (Zeckendorf).
+/// The fibonacci code writes that sum as bits for F(2), F(3), ... followed by an extra 1;
+/// since the representation never has two adjacent 1s, "11" marks the end of each codeword.
+pub struct FibonacciCodec {
+    // F(2), F(3), ..., F(93): 1, 2, 3, 5, ...
+    fibonacci: Vec<u64>,
+}
+
+impl FibonacciCodec {
+    pub fn new() -> FibonacciCodec {
+        let mut cache: HashMap<usize, u128> = HashMap::new();
+        let fibonacci = (2..=MAX_U64_FIBONACCI_N)
+            .map(|n| fibonacci_cached(n, &mut cache).expect("F(93) fits into u128") as u64)
+            .collect();
+        FibonacciCodec { fibonacci }
+    }
+
+    /// Fibonacci numbers summing up to `n`, largest first; empty for 0.
+    pub fn zeckendorf(&self, mut n: u64) -> Vec<u64> {
+        let mut parts: Vec<u64> = Vec::new();
+        for &f in self.fibonacci.iter().rev() {
+            if f <= n {
+                parts.push(f);
+                n -= f;
+            }
+        }
+        parts
+    }
+
+    /// The codeword of `n`, including the terminating 1 bit.
+    pub fn codeword(&self, mut n: u64) -> Result<Vec<bool>, CodingError> {
+        if n == 0 {
+            return Err(CodingError::Zero);
+        }
+        let highest = self.fibonacci.iter().rposition(|&f| f <= n).expect("n >= 1 = F(2)");
+        let mut bits = vec![false; highest + 2];
+        for i in (0..=highest).rev() {
+            if self.fibonacci[i] <= n {
+                bits[i] = true;
+                n -= self.fibonacci[i];
+            }
+        }
+        bits[highest + 1] = true;
+        Ok(bits)
+    }
+
+    /// Encodes all values into a packed bit stream, most significant bit of each byte first.
+    /// The last byte is padded with 0 bits.
+    pub fn encode(&self, values: &[u64]) -> Result<Vec<u8>, CodingError> {
+        let mut writer = BitWriter::new();
+        for &value in values {
+            for bit in self.codeword(value)? {
+                writer.push(bit);
+            }
+        }
+        Ok(writer.into_bytes())
+    }
+
+    /// Decodes a stream of codewords given as single bits.
+    ///
+    /// Trailing 0 bits after the last codeword are accepted as padding, as long as there are fewer than 8.
+    pub fn decode_bits(&self, bits: impl IntoIterator<Item=bool>) -> Result<Vec<u64>, CodingError> {
+        let mut values: Vec<u64> = Vec::new();
+        let mut value: u64 = 0;
+        let mut position: usize = 0;
+        let mut previous_bit = false;
+        for bit in bits {
+            if bit && previous_bit {
+                values.push(value);
+                value = 0;
+                position = 0;
+                previous_bit = false;
+                continue;
+            }
+            if bit {
+                let f = self.fibonacci.get(position).ok_or(CodingError::Overflow)?;
+                value = value.checked_add(*f).ok_or(CodingError::Overflow)?;
+            }
+            previous_bit = bit;
+            position += 1;
+        }
+
+        if value != 0 || position >= 8 {
+            return Err(CodingError::Truncated);
+        }
+        Ok(values)
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> Result<Vec<u64>, CodingError> {
+        let bits = bytes.iter().flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1 == 1));
+        self.decode_bits(bits)
+    }
+}
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    bit_len: usize,
+}
+
+impl BitWriter {
+    fn new() -> BitWriter {
+        BitWriter { bytes: Vec::new(), bit_len: 0 }
+    }
+
+    fn push(&mut self, bit: bool) {
+        if self.bit_len.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        if bit {
+            *self.bytes.last_mut().unwrap() |= 0x80 >> (self.bit_len % 8);
+        }
+        self.bit_len += 1;
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bit_string(bits: &[bool]) -> String {
+        bits.iter().map(|&b| if b { '1' } else { '0' }).collect()
+    }
+
+    #[test]
+    fn zeckendorf_representation() {
+        let codec = FibonacciCodec::new();
+        assert_eq!(codec.zeckendorf(0), Vec::<u64>::new());
+        assert_eq!(codec.zeckendorf(1), vec![1]);
+        assert_eq!(codec.zeckendorf(4), vec![3, 1]);
+        assert_eq!(codec.zeckendorf(100), vec![89, 8, 3]);
+        for n in 1..10_000u64 {
+            let parts = codec.zeckendorf(n);
+            assert_eq!(parts.iter().sum::<u64>(), n);
+            // no two consecutive fibonacci numbers, so "11" only shows up as terminator
+            let codeword = bit_string(&codec.codeword(n).unwrap());
+            assert_eq!(codeword.find("11"), Some(codeword.len() - 2), "{}: {}", n, codeword);
+        }
+    }
+
+    #[test]
+    fn known_codewords() {
+        let codec = FibonacciCodec::new();
+        assert_eq!(bit_string(&codec.codeword(1).unwrap()), "11");
+        assert_eq!(bit_string(&codec.codeword(2).unwrap()), "011");
+        assert_eq!(bit_string(&codec.codeword(3).unwrap()), "0011");
+        assert_eq!(bit_string(&codec.codeword(4).unwrap()), "1011");
+        assert_eq!(bit_string(&codec.codeword(11).unwrap()), "001011");
+        assert_eq!(codec.codeword(0), Err(CodingError::Zero));
+    }
+
+    #[test]
+    fn round_trip() {
+        let codec = FibonacciCodec::new();
+        let mut values: Vec<u64> = (1..5_000).collect();
+        values.extend([u64::MAX, u64::MAX - 1, 12_200_160_415_121_876_738, 1 << 63, 1]);
+        let encoded = codec.encode(&values).unwrap();
+        assert_eq!(codec.decode(&encoded).unwrap(), values);
+    }
+
+    #[test]
+    fn round_trip_of_empty_stream() {
+        let codec = FibonacciCodec::new();
+        assert_eq!(codec.encode(&[]).unwrap(), Vec::<u8>::new());
+        assert_eq!(codec.decode(&[]).unwrap(), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn rejects_zero() {
+        let codec = FibonacciCodec::new();
+        assert_eq!(codec.encode(&[3, 0, 1]), Err(CodingError::Zero));
+    }
+
+    #[test]
+    fn detects_truncated_input() {
+        let codec = FibonacciCodec::new();
+        // "1011" (4) followed by the beginning of "0011" (3)
+        assert_eq!(codec.decode_bits([true, false, true, true, false, false, true]), Err(CodingError::Truncated));
+        assert_eq!(codec.decode(&[0b1011_0000, 0]), Err(CodingError::Truncated));
+    }
+
+    #[test]
+    fn detects_overflow() {
+        let codec = FibonacciCodec::new();
+        // a 1 bit right after the position of F(93)
+        let bits = std::iter::repeat_n(false, 92).chain([true, false, true, true]);
+        assert_eq!(codec.decode_bits(bits), Err(CodingError::Overflow));
+    }
+}
@@ -0,0 +1,169 @@
+use crate::primes_sieve_of_eratosthenes;
+use crate::segmented::primes_in_range;
+
+/// A way to find all primes up to a limit, so the algorithms can be swapped at runtime.
+pub trait Sieve {
+    /// All primes p <= limit in ascending order.
+    fn primes_up_to(&self, limit: usize) -> Vec<usize>;
+}
+
+/// Names accepted by `by_name`, the first one is the default.
+pub const ALGORITHM_NAMES: [&str; 4] = ["segmented", "eratosthenes", "atkin", "wheel"];
+
+pub fn by_name(name: &str) -> Option<Box<dyn Sieve>> {
+    match name {
+        "segmented" => Some(Box::new(Segmented)),
+        "eratosthenes" => Some(Box::new(Eratosthenes)),
+        "atkin" => Some(Box::new(Atkin)),
+        "wheel" => Some(Box::new(Wheel)),
+        _ => None,
+    }
+}
+
+/// The classic sieve, one bool per number.
+pub struct Eratosthenes;
+
+impl Sieve for Eratosthenes {
+    fn primes_up_to(&self, limit: usize) -> Vec<usize> {
+        primes_sieve_of_eratosthenes(limit)
+    }
+}
+
+/// Eratosthenes one cache sized segment at a time.
+pub struct Segmented;
+
+impl Sieve for Segmented {
+    fn primes_up_to(&self, limit: usize) -> Vec<usize> {
+        primes_in_range(0, limit as u64).into_iter().map(|p| p as usize).collect()
+    }
+}
+
+/// Sieve of Atkin: n > 3 is prime iff it is squarefree and one of the quadratic forms below
+/// has an odd number of solutions for it, depending on n mod 12.
+pub struct Atkin;
+
+impl Sieve for Atkin {
+    fn primes_up_to(&self, limit: usize) -> Vec<usize> {
+        let mut sieve: Vec<bool> = vec![false; limit + 1];
+
+        let mut x: usize = 1;
+        while x * x <= limit {
+            let mut y: usize = 1;
+            while y * y <= limit {
+                let (xx, yy) = (x * x, y * y);
+                let n = 4 * xx + yy;
+                if n <= limit && (n % 12 == 1 || n % 12 == 5) {
+                    sieve[n] = !sieve[n];
+                }
+                let n = 3 * xx + yy;
+                if n <= limit && n % 12 == 7 {
+                    sieve[n] = !sieve[n];
+                }
+                if x > y {
+                    let n = 3 * xx - yy;
+                    if n <= limit && n % 12 == 11 {
+                        sieve[n] = !sieve[n];
+                    }
+                }
+                y += 1;
+            }
+            x += 1;
+        }
+
+        // the forms also let through multiples of prime squares
+        let mut r: usize = 5;
+        while r * r <= limit {
+            if sieve[r] {
+                for multiple in (r * r..=limit).step_by(r * r) {
+                    sieve[multiple] = false;
+                }
+            }
+            r += 1;
+        }
+
+        let mut primes: Vec<usize> = [2, 3].into_iter().filter(|&p| p <= limit).collect();
+        primes.extend((5..=limit).filter(|&n| sieve[n]));
+        primes
+    }
+}
+
+// the wheel: numbers coprime to 2 * 3 * 5 * 7, 48 out of every 210
+const WHEEL_PRIMES: [usize; 4] = [2, 3, 5, 7];
+const WHEEL: usize = 210;
+const SPOKES: usize = 48;
+
+/// Eratosthenes on the numbers coprime to 210 only, which needs less than a quarter
+/// of the memory and skips the multiples of 2, 3, 5 and 7 entirely.
+pub struct Wheel;
+
+impl Sieve for Wheel {
+    fn primes_up_to(&self, limit: usize) -> Vec<usize> {
+        let spokes: Vec<usize> = (1..WHEEL).filter(|n| WHEEL_PRIMES.iter().all(|p| n % p != 0)).collect();
+        // index of every residue on the wheel, SPOKES for the ones that are not
+        let mut spoke_of_residue: Vec<usize> = vec![SPOKES; WHEEL];
+        for (i, &spoke) in spokes.iter().enumerate() {
+            spoke_of_residue[spoke] = i;
+        }
+        let value = |i: usize| i / SPOKES * WHEEL + spokes[i % SPOKES];
+        let index = |n: usize| n / WHEEL * SPOKES + spoke_of_residue[n % WHEEL];
+
+        // the candidates are value(0) = 1, value(1) = 11, ... up to the limit
+        let candidates = limit / WHEEL * SPOKES + spokes.iter().filter(|&&s| s <= limit % WHEEL).count();
+        let mut sieve: Vec<bool> = vec![true; candidates];
+        if candidates > 0 {
+            sieve[0] = false; // 1 is not prime
+        }
+
+        for i in 1..candidates {
+            let p = value(i);
+            if p > limit / p {
+                break;
+            }
+            if !sieve[i] {
+                continue;
+            }
+            // multiples of p with a cofactor on the wheel, smaller cofactors were handled by smaller primes
+            for j in i.. {
+                let cofactor = value(j);
+                if cofactor > limit / p {
+                    break;
+                }
+                sieve[index(p * cofactor)] = false;
+            }
+        }
+
+        let mut primes: Vec<usize> = WHEEL_PRIMES.into_iter().filter(|&p| p <= limit).collect();
+        primes.extend(sieve.iter().enumerate().filter(|(_, &is_prime)| is_prime).map(|(i, _)| value(i)));
+        primes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_sieves() -> Vec<(&'static str, Box<dyn Sieve>)> {
+        ALGORITHM_NAMES.iter().map(|&name| (name, by_name(name).expect("every listed name is known"))).collect()
+    }
+
+    #[test]
+    fn all_sieves_agree_on_small_limits() {
+        for limit in 0..=1_000 {
+            let expected = Eratosthenes.primes_up_to(limit);
+            for (name, sieve) in all_sieves() {
+                assert_eq!(sieve.primes_up_to(limit), expected, "{} up to {}", name, limit);
+            }
+        }
+    }
+
+    #[test]
+    fn all_sieves_agree_up_to_10_pow_7() {
+        let limit = 10_000_000;
+        let expected = Eratosthenes.primes_up_to(limit);
+        assert_eq!(expected.len(), 664_579);
+        assert!(by_name("sundaram").is_none());
+        for (name, sieve) in all_sieves() {
+            assert!(sieve.primes_up_to(limit) == expected, "{} differs up to {}", name, limit);
+        }
+    }
+}
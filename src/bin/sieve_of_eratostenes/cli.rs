@@ -0,0 +1,328 @@
+use std::path::PathBuf;
+
+use crate::algorithms::ALGORITHM_NAMES;
+use crate::error::SieveError;
+use crate::prime_count::MAX_COUNT_LIMIT;
+use crate::prime_table::TableFormat;
+
+// the linear sieve behind --table takes 9 bytes per number, so this is about 4.5 GB already
+const MAX_TABLE_LIMIT: u64 = 500_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    List,
+    Count,
+    /// the n-th prime within the range, counting from 1
+    Nth(u64),
+    Last,
+    /// number of Goldbach partitions and the one with the smallest prime, for every even n in the range
+    Goldbach,
+    /// check that every even n in the range is the sum of two primes
+    GoldbachVerify,
+    /// gap, constellation and residue class statistics of the range
+    Stats,
+    /// smallest prime factor, μ(n), φ(n) and the factorization of every n in the range, from the linear sieve
+    Table,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Plain,
+    Csv,
+    Json,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    /// start of the range, inclusive
+    pub from: u64,
+    /// end of the range, inclusive
+    pub limit: u64,
+    pub mode: Mode,
+    pub format: Format,
+    pub threads: usize,
+    /// sieve the whole range [0, limit] with one of the `algorithms` instead of streaming segments
+    pub algorithm: Option<&'static str>,
+    /// take the primes from a table written by an earlier --export instead of sieving
+    pub import: Option<PathBuf>,
+    /// write the list of primes to this file instead of stdout
+    pub export: Option<(PathBuf, TableFormat)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Help,
+    Sieve(Options),
+    /// test a single number with Miller-Rabin instead of sieving
+    IsPrime { n: u128, format: Format },
+    /// prime factorization of a single number, with its divisor count, σ(n) and φ(n)
+    Factor { n: u64, format: Format },
+}
+
+fn usage_error(message: String) -> SieveError {
+    SieveError::Usage(message)
+}
+
+fn parse_number<T: std::str::FromStr>(option: &str, value: Option<&str>) -> Result<T, SieveError> {
+    let value = value.ok_or_else(|| usage_error(format!("{} needs a value", option)))?;
+    value.parse::<T>()
+        .map_err(|_| usage_error(format!("{} needs a non-negative integer, got '{}'", option, value)))
+}
+
+fn parse_format(value: Option<&str>) -> Result<Format, SieveError> {
+    match value {
+        Some("plain") => Ok(Format::Plain),
+        Some("csv") => Ok(Format::Csv),
+        Some("json") => Ok(Format::Json),
+        other => Err(usage_error(format!("unknown format '{}'", other.unwrap_or_default()))),
+    }
+}
+
+// the commands working on a single number: `<command> [--format ...] <n>`
+fn parse_single_number<T: std::str::FromStr>(command: &str, args: &[String]) -> Result<Option<(T, Format)>, SieveError> {
+    let mut n: Option<T> = None;
+    let mut format = Format::Plain;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--format" => format = parse_format(args.next().map(String::as_str))?,
+            option if option.starts_with("--") => {
+                return Err(usage_error(format!("unknown option '{}' for {}", option, command)));
+            }
+            value if n.is_none() => n = Some(parse_number("<n>", Some(value))?),
+            value => return Err(usage_error(format!("unexpected argument '{}'", value))),
+        }
+    }
+
+    let n = n.ok_or_else(|| usage_error(format!("{} needs a number", command)))?;
+    Ok(Some((n, format)))
+}
+
+pub fn parse_args(args: &[String]) -> Result<Command, SieveError> {
+    match args.get(1).map(String::as_str) {
+        Some("is-prime") => {
+            return Ok(parse_single_number("is-prime", &args[2..])?
+                .map_or(Command::Help, |(n, format)| Command::IsPrime { n, format }));
+        }
+        Some("factor") => {
+            return match parse_single_number("factor", &args[2..])? {
+                None => Ok(Command::Help),
+                Some((0, _)) => Err(usage_error(String::from("0 has no prime factorization"))),
+                Some((n, format)) => Ok(Command::Factor { n, format }),
+            };
+        }
+        _ => {}
+    }
+
+    let mut from: u64 = 0;
+    let mut limit: Option<u64> = None;
+    let mut mode: Option<Mode> = None;
+    let mut format = Format::Plain;
+    let mut threads: usize = 1;
+    let mut algorithm: Option<&'static str> = None;
+    let mut import: Option<PathBuf> = None;
+    let mut export: Option<(PathBuf, TableFormat)> = None;
+
+    let mut set_mode = |new_mode: Mode| match mode {
+        Some(_) => Err(usage_error(String::from("only one of --list, --count, --nth, --last, --stats, --goldbach, --goldbach-verify and --table can be given"))),
+        None => {
+            mode = Some(new_mode);
+            Ok(())
+        }
+    };
+
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--from" => from = parse_number(arg, args.next().map(String::as_str))?,
+            "--list" => set_mode(Mode::List)?,
+            "--count" => set_mode(Mode::Count)?,
+            "--last" => set_mode(Mode::Last)?,
+            "--stats" => set_mode(Mode::Stats)?,
+            "--goldbach" => set_mode(Mode::Goldbach)?,
+            "--goldbach-verify" => set_mode(Mode::GoldbachVerify)?,
+            "--table" => set_mode(Mode::Table)?,
+            "--nth" => match parse_number::<u64>(arg, args.next().map(String::as_str))? {
+                0 => return Err(usage_error(String::from("--nth counts from 1"))),
+                n => set_mode(Mode::Nth(n))?,
+            },
+            "--format" => format = parse_format(args.next().map(String::as_str))?,
+            "--threads" => match parse_number::<usize>(arg, args.next().map(String::as_str))? {
+                0 => return Err(usage_error(String::from("--threads needs a positive number"))),
+                n => threads = n,
+            },
+            "--algorithm" => {
+                let name = args.next().map(String::as_str).unwrap_or_default();
+                match ALGORITHM_NAMES.iter().find(|&&known| known == name) {
+                    Some(&known) => algorithm = Some(known),
+                    None => return Err(usage_error(format!("unknown algorithm '{}'", name))),
+                }
+            }
+            "--import" => match args.next() {
+                Some(path) => import = Some(PathBuf::from(path)),
+                None => return Err(usage_error(String::from("--import needs a file"))),
+            },
+            "--export" | "--export-text" => {
+                let format = if arg == "--export" { TableFormat::Binary } else { TableFormat::Text };
+                match args.next() {
+                    Some(path) => export = Some((PathBuf::from(path), format)),
+                    None => return Err(usage_error(format!("{} needs a file", arg))),
+                }
+            }
+            option if option.starts_with("--") => return Err(usage_error(format!("unknown option '{}'", option))),
+            value if limit.is_none() => limit = Some(parse_number("<limit>", Some(value))?),
+            value => return Err(usage_error(format!("unexpected argument '{}'", value))),
+        }
+    }
+
+    let limit = limit.ok_or_else(|| usage_error(String::from("please provide a limit")))?;
+    if mode == Some(Mode::Table) && limit > MAX_TABLE_LIMIT {
+        return Err(usage_error(format!("--table supports limits up to {}", MAX_TABLE_LIMIT)));
+    }
+    let mode = mode.unwrap_or(Mode::List);
+    // --last counts the primes up to the one it finds
+    let counted = matches!(mode, Mode::Count | Mode::Last);
+    if counted && import.is_none() && algorithm.is_none() && limit > MAX_COUNT_LIMIT {
+        let option = if mode == Mode::Count { "--count" } else { "--last" };
+        return Err(usage_error(format!("{} supports limits up to {}", option, MAX_COUNT_LIMIT)));
+    }
+    if export.is_some() && mode != Mode::List {
+        return Err(usage_error(String::from("--export only works with --list")));
+    }
+    // these modes sieve on their own
+    let own_sieve = matches!(mode, Mode::Table | Mode::Goldbach | Mode::GoldbachVerify);
+    if import.is_some() && own_sieve {
+        return Err(usage_error(String::from("--import can not be combined with --table and the Goldbach modes")));
+    }
+    if algorithm.is_some() {
+        if threads > 1 || import.is_some() || own_sieve {
+            return Err(usage_error(String::from(
+                "--algorithm can not be combined with --threads, --import, --table and the Goldbach modes")));
+        }
+        if usize::try_from(limit).is_err() {
+            return Err(usage_error(format!("--algorithm supports limits up to {}", usize::MAX)));
+        }
+    }
+    Ok(Command::Sieve(Options { from, limit, mode, format, threads, algorithm, import, export }))
+}
+
+pub fn print_usage(program: &str) {
+    println!("Usage: {} [options] <limit>", program);
+    println!("       {} is-prime [--format plain|csv|json] <n>", program);
+    println!("       {} factor [--format plain|csv|json] <n>", program);
+    println!();
+    println!("Sieves the primes p with from <= p <= limit.");
+    println!();
+    println!("Options:");
+    println!("  --from <number>            start of the range (default 0)");
+    println!("  --list                     print all primes (default)");
+    println!("  --count                    print the number of primes, counted with the Meissel-Lehmer method,");
+    println!("                             for limits up to 10^14");
+    println!("  --nth <n>                  print the n-th prime of the range, counting from 1");
+    println!("  --last                     print the largest prime of the range, for limits up to 10^14");
+    println!("  --stats                    print gaps, twin/cousin/sexy pairs, k-tuplets and the Chebyshev bias of the range");
+    println!("  --goldbach                 print the number of Goldbach partitions p + q and the one with the");
+    println!("                             smallest p for every even number in the range; takes minutes from 10^7 on");
+    println!("  --goldbach-verify          check that every even number in the range is the sum of two primes");
+    println!("  --table                    print the smallest prime factor, mu(n), phi(n) and the factorization of");
+    println!("                             every n in the range; sieves all of [0, limit] at 9 bytes per number");
+    println!("  --format plain|csv|json    output format (default plain)");
+    println!("  --threads <number>         sieve with several threads (default 1)");
+    println!("  --algorithm <name>         sieve [0, limit] at once with segmented, eratosthenes, atkin or wheel");
+    println!("  --export <file>            write the primes to a compact binary table instead of printing them");
+    println!("  --export-text <file>       write the primes to a text table instead of printing them");
+    println!("  --import <file>            read the primes from an exported table instead of sieving");
+    println!("  -h, --help                 print this help");
+    println!();
+    println!("is-prime tests a single n with Miller-Rabin: exact up to 2^64, a strong probable-prime test beyond.");
+    println!("factor factorizes 0 < n < 2^64 with trial division and Pollard's rho.");
+    println!();
+    println!("Exit codes: 0 success, 1 invalid arguments, 2 no such prime in the range or a Goldbach counterexample,");
+    println!("            3 i/o error");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Command, SieveError> {
+        let args: Vec<String> = std::iter::once("sieve").chain(args.split_whitespace()).map(String::from).collect();
+        parse_args(&args)
+    }
+
+    fn usage_message(args: &str) -> String {
+        match parse(args) {
+            Err(SieveError::Usage(message)) => message,
+            other => panic!("expected a usage error for '{}', got {:?}", args, other),
+        }
+    }
+
+    fn options(args: &str) -> Options {
+        match parse(args) {
+            Ok(Command::Sieve(options)) => options,
+            other => panic!("expected sieve options for '{}', got {:?}", args, other),
+        }
+    }
+
+    #[test]
+    fn parses_sieve_options() {
+        assert_eq!(options("100"), Options {
+            from: 0, limit: 100, mode: Mode::List, format: Format::Plain, threads: 1,
+            algorithm: None, import: None, export: None,
+        });
+        let counted = options("--from 10 --count --format json --threads 4 1000");
+        assert_eq!((counted.from, counted.limit, counted.mode, counted.format, counted.threads),
+                   (10, 1_000, Mode::Count, Format::Json, 4));
+        assert_eq!(options("--nth 3 100").mode, Mode::Nth(3));
+        assert_eq!(options("--algorithm atkin --last 100").algorithm, Some("atkin"));
+        assert_eq!(options("--export-text primes.txt 100").export, Some((PathBuf::from("primes.txt"), TableFormat::Text)));
+        assert_eq!(options("--list --export primes.bin 100").export, Some((PathBuf::from("primes.bin"), TableFormat::Binary)));
+        assert_eq!(options("--import primes.bin --stats 100").import, Some(PathBuf::from("primes.bin")));
+    }
+
+    #[test]
+    fn parses_subcommands_and_help() {
+        assert_eq!(parse("--help 100").unwrap(), Command::Help);
+        assert_eq!(parse("-h").unwrap(), Command::Help);
+        assert_eq!(parse("is-prime 97").unwrap(), Command::IsPrime { n: 97, format: Format::Plain });
+        assert_eq!(parse("factor --format csv 12").unwrap(), Command::Factor { n: 12, format: Format::Csv });
+        assert_eq!(usage_message("factor 0"), "0 has no prime factorization");
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert_eq!(usage_message(""), "please provide a limit");
+        assert_eq!(usage_message("--count"), "please provide a limit");
+        assert_eq!(usage_message("--count --last 100"),
+                   "only one of --list, --count, --nth, --last, --stats, --goldbach, --goldbach-verify and --table can be given");
+        assert_eq!(usage_message("--nth 0 100"), "--nth counts from 1");
+        assert_eq!(usage_message("--threads 0 100"), "--threads needs a positive number");
+        assert_eq!(usage_message("--from -1 100"), "--from needs a non-negative integer, got '-1'");
+        assert_eq!(usage_message("--from"), "--from needs a value");
+        assert_eq!(usage_message("100 200"), "unexpected argument '200'");
+        assert_eq!(usage_message("--frobnicate 100"), "unknown option '--frobnicate'");
+        assert_eq!(usage_message("--algorithm bogus 100"), "unknown algorithm 'bogus'");
+        assert_eq!(usage_message("--table 500000001"), "--table supports limits up to 500000000");
+        assert_eq!(usage_message("--count --export primes.bin 100"), "--export only works with --list");
+        assert_eq!(usage_message("--import primes.bin --goldbach 100"),
+                   "--import can not be combined with --table and the Goldbach modes");
+        for conflict in ["--algorithm wheel --threads 2 100", "--algorithm wheel --import primes.bin 100",
+                         "--algorithm wheel --table 100"] {
+            assert_eq!(usage_message(conflict),
+                       "--algorithm can not be combined with --threads, --import, --table and the Goldbach modes");
+        }
+    }
+
+    #[test]
+    fn limits_the_count() {
+        assert!(parse("--count 100000000000000").is_ok());
+        assert_eq!(usage_message("--count 100000000000001"), "--count supports limits up to 100000000000000");
+        assert_eq!(usage_message("--count 18446744073709551615"), "--count supports limits up to 100000000000000");
+        assert_eq!(usage_message("--last 100000000000001"), "--last supports limits up to 100000000000000");
+        // listing streams the range, it needs no such limit
+        assert!(parse("--list 18446744073709551615").is_ok());
+    }
+}
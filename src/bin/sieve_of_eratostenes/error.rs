@@ -0,0 +1,38 @@
+use std::fmt;
+use std::io;
+
+/// Failures of the sieve binary, each kind with its own exit code.
+#[derive(Debug)]
+pub enum SieveError {
+    /// the arguments could not be understood
+    Usage(String),
+    /// the requested prime does not exist within the range
+    NotFound(String),
+    Io(io::Error),
+}
+
+impl SieveError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            SieveError::Usage(_) => 1,
+            SieveError::NotFound(_) => 2,
+            SieveError::Io(_) => 3,
+        }
+    }
+}
+
+impl fmt::Display for SieveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SieveError::Usage(message) => write!(f, "{}", message),
+            SieveError::NotFound(message) => write!(f, "{}", message),
+            SieveError::Io(e) => write!(f, "i/o error: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for SieveError {
+    fn from(e: io::Error) -> Self {
+        SieveError::Io(e)
+    }
+}
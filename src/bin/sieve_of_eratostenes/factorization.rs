@@ -0,0 +1,221 @@
+use crate::primality::{is_prime_u64, mul_mod_u64};
+use crate::primes_sieve_of_eratosthenes;
+
+// trial division takes care of all prime factors below this, Pollard's rho of the rest
+pub const DEFAULT_TRIAL_LIMIT: usize = 1 << 16;
+
+// number of steps between two gcd computations in Brent's variant
+const BATCH_SIZE: u64 = 128;
+
+/// Factorizes u64 numbers into `(prime, exponent)` pairs, ordered by prime.
+pub struct Factorizer {
+    small_primes: Vec<u64>,
+}
+
+impl Factorizer {
+    /// Sieves the primes up to `trial_limit` once, to be used for trial division of every number.
+    pub fn new(trial_limit: usize) -> Factorizer {
+        Factorizer { small_primes: primes_sieve_of_eratosthenes(trial_limit as u64) }
+    }
+
+    /// Panics for n = 0, which has no factorization. 1 yields no factors.
+    pub fn factorize(&self, n: u64) -> Vec<(u64, u32)> {
+        assert!(n > 0, "0 has no prime factorization");
+        let mut factors: Vec<(u64, u32)> = Vec::new();
+        let mut rest = n;
+        for &p in self.small_primes.iter() {
+            if p > rest / p {
+                break;
+            }
+            let mut exponent: u32 = 0;
+            while rest.is_multiple_of(p) {
+                rest /= p;
+                exponent += 1;
+            }
+            if exponent > 0 {
+                factors.push((p, exponent));
+            }
+        }
+        if rest == 1 {
+            return factors;
+        }
+
+        // rest is either prime or has only factors beyond the trial division primes
+        let mut large_primes: Vec<u64> = Vec::new();
+        split_into_primes(rest, &mut large_primes);
+        large_primes.sort_unstable();
+        for p in large_primes {
+            match factors.last_mut() {
+                Some((last, exponent)) if *last == p => *exponent += 1,
+                _ => factors.push((p, 1)),
+            }
+        }
+        factors
+    }
+}
+
+impl Default for Factorizer {
+    fn default() -> Self {
+        Factorizer::new(DEFAULT_TRIAL_LIMIT)
+    }
+}
+
+// n > 1, usually without small factors
+fn split_into_primes(n: u64, primes: &mut Vec<u64>) {
+    if is_prime_u64(n) {
+        primes.push(n);
+        return;
+    }
+    // the rho sequence x² + c does not work for even n
+    if n.is_multiple_of(2) {
+        primes.push(2);
+        split_into_primes(n / 2, primes);
+        return;
+    }
+    let divisor = (1..).find_map(|c| pollard_brent(n, c)).unwrap();
+    split_into_primes(divisor, primes);
+    split_into_primes(n / divisor, primes);
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Brent's variant of Pollard's rho for the pseudo random sequence x² + c mod n.
+/// Returns a proper divisor of the odd composite n, or `None` if this c did not find one.
+fn pollard_brent(n: u64, c: u64) -> Option<u64> {
+    // x² + c without overflowing, both terms are < n
+    let f = |x: u64| ((mul_mod_u64(x, x, n) as u128 + c as u128) % n as u128) as u64;
+
+    let (mut x, mut y, mut saved_y) = (2u64, 2u64, 2u64);
+    let mut q: u64 = 1;
+    let mut g: u64 = 1;
+    let mut r: u64 = 1;
+    while g == 1 {
+        x = y;
+        for _ in 0..r {
+            y = f(y);
+        }
+        let mut k: u64 = 0;
+        while k < r && g == 1 {
+            saved_y = y;
+            for _ in 0..BATCH_SIZE.min(r - k) {
+                y = f(y);
+                q = mul_mod_u64(q, x.abs_diff(y), n);
+            }
+            g = gcd(q, n);
+            k += BATCH_SIZE;
+        }
+        r *= 2;
+    }
+    if g == n {
+        // the batch overshot, redo its steps one at a time
+        loop {
+            saved_y = f(saved_y);
+            g = gcd(x.abs_diff(saved_y), n);
+            if g > 1 {
+                break;
+            }
+        }
+    }
+    if g == n { None } else { Some(g) }
+}
+
+/// Number of divisors of n, given its factorization.
+pub fn divisor_count(factors: &[(u64, u32)]) -> u64 {
+    factors.iter().map(|&(_, e)| e as u64 + 1).product()
+}
+
+/// σ(n), the sum of all divisors of n, given its factorization.
+pub fn divisor_sum(factors: &[(u64, u32)]) -> u128 {
+    factors.iter()
+        .map(|&(p, e)| {
+            // 1 + p + p² + ... + p^e
+            let mut sum: u128 = 1;
+            let mut power: u128 = 1;
+            for _ in 0..e {
+                power *= p as u128;
+                sum += power;
+            }
+            sum
+        })
+        .product()
+}
+
+/// Euler's φ(n), the count of numbers in [1, n] coprime to n, given its factorization.
+pub fn euler_phi(factors: &[(u64, u32)]) -> u64 {
+    factors.iter().map(|&(p, e)| (p - 1) * p.pow(e - 1)).product()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn factorize_by_trial_division(mut n: u64) -> Vec<(u64, u32)> {
+        let mut factors: Vec<(u64, u32)> = Vec::new();
+        let mut d: u64 = 2;
+        while d * d <= n {
+            let mut exponent: u32 = 0;
+            while n.is_multiple_of(d) {
+                n /= d;
+                exponent += 1;
+            }
+            if exponent > 0 {
+                factors.push((d, exponent));
+            }
+            d += 1;
+        }
+        if n > 1 {
+            factors.push((n, 1));
+        }
+        factors
+    }
+
+    #[test]
+    fn agrees_with_trial_division() {
+        let factorizer = Factorizer::default();
+        for n in 1..20_000 {
+            assert_eq!(factorizer.factorize(n), factorize_by_trial_division(n), "n = {}", n);
+        }
+    }
+
+    #[test]
+    fn uses_pollard_rho_beyond_the_trial_limit() {
+        // a tiny trial limit, so that rho has to do the work
+        let factorizer = Factorizer::new(10);
+        for n in 1..20_000 {
+            assert_eq!(factorizer.factorize(n), factorize_by_trial_division(n), "n = {}", n);
+        }
+    }
+
+    #[test]
+    fn factorizes_large_numbers() {
+        let factorizer = Factorizer::default();
+        assert_eq!(factorizer.factorize(u64::MAX),
+                   vec![(3, 1), (5, 1), (17, 1), (257, 1), (641, 1), (65_537, 1), (6_700_417, 1)]);
+        // the two largest primes below 2^32
+        assert_eq!(factorizer.factorize(4_294_967_291 * 4_294_967_279), vec![(4_294_967_279, 1), (4_294_967_291, 1)]);
+        assert_eq!(factorizer.factorize(4_294_967_291 * 4_294_967_291), vec![(4_294_967_291, 2)]);
+        assert_eq!(factorizer.factorize(18_446_744_073_709_551_557), vec![(18_446_744_073_709_551_557, 1)]);
+        assert_eq!(factorizer.factorize(1 << 63), vec![(2, 63)]);
+        // 1000003 * 1000033 * 1000037
+        assert_eq!(factorizer.factorize(1_000_003 * 1_000_033 * 1_000_037),
+                   vec![(1_000_003, 1), (1_000_033, 1), (1_000_037, 1)]);
+    }
+
+    #[test]
+    fn divisor_functions_agree_with_counting() {
+        let factorizer = Factorizer::default();
+        for n in 1..2_000u64 {
+            let factors = factorizer.factorize(n);
+            let divisors: Vec<u64> = (1..=n).filter(|d| n % d == 0).collect();
+            assert_eq!(divisor_count(&factors), divisors.len() as u64, "n = {}", n);
+            assert_eq!(divisor_sum(&factors), divisors.iter().map(|&d| d as u128).sum::<u128>(), "n = {}", n);
+            let coprime = (1..=n).filter(|&k| gcd(k, n) == 1).count() as u64;
+            assert_eq!(euler_phi(&factors), coprime, "n = {}", n);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+/// Euler's linear sieve: every composite is crossed out exactly once, by its smallest prime factor,
+/// which yields that factor for every n <= limit and, along the way, the multiplicative
+/// functions μ(n) and φ(n).
+///
+/// Takes 9 bytes per number, so it is meant for bulk factorization of numbers below a few 10^8.
+pub struct LinearSieve {
+    // smallest_prime_factor[n], 0 for n < 2
+    smallest_prime_factor: Vec<u32>,
+    mobius: Vec<i8>,
+    phi: Vec<u32>,
+}
+
+impl LinearSieve {
+    /// Sieves all n up to and including `limit`.
+    pub fn new(limit: u32) -> LinearSieve {
+        let size = limit as usize + 1;
+        let mut smallest_prime_factor: Vec<u32> = vec![0; size];
+        let mut mobius: Vec<i8> = vec![0; size];
+        let mut phi: Vec<u32> = vec![0; size];
+        let mut primes: Vec<u32> = Vec::new();
+        if limit >= 1 {
+            mobius[1] = 1;
+            phi[1] = 1;
+        }
+
+        for i in 2..size {
+            if smallest_prime_factor[i] == 0 {
+                smallest_prime_factor[i] = i as u32;
+                mobius[i] = -1;
+                phi[i] = i as u32 - 1;
+                primes.push(i as u32);
+            }
+            for &p in primes.iter() {
+                let p = p as usize;
+                // i * p has a smaller prime factor than p once p > spf(i), it is crossed out later
+                if p > smallest_prime_factor[i] as usize || i * p >= size {
+                    break;
+                }
+                let multiple = i * p;
+                smallest_prime_factor[multiple] = p as u32;
+                if p == smallest_prime_factor[i] as usize {
+                    // p divides i: μ vanishes, φ grows by the full factor p
+                    mobius[multiple] = 0;
+                    phi[multiple] = phi[i] * p as u32;
+                } else {
+                    mobius[multiple] = -mobius[i];
+                    phi[multiple] = phi[i] * (p as u32 - 1);
+                }
+            }
+        }
+        LinearSieve { smallest_prime_factor, mobius, phi }
+    }
+
+    #[cfg(test)]
+    fn limit(&self) -> u32 {
+        (self.smallest_prime_factor.len() - 1) as u32
+    }
+
+    // the primes are exactly the numbers which are their own smallest prime factor
+    #[cfg(test)]
+    fn primes(&self) -> Vec<u32> {
+        (2..=self.limit()).filter(|&n| self.smallest_prime_factor(n) == n).collect()
+    }
+
+    /// The smallest prime factor of n, 0 for n < 2; panics if `n` is beyond the limit.
+    pub fn smallest_prime_factor(&self, n: u32) -> u32 {
+        self.smallest_prime_factor[n as usize]
+    }
+
+    /// Möbius μ(n): 0 if n has a squared prime factor, else (-1)^(number of prime factors).
+    pub fn mobius(&self, n: u32) -> i8 {
+        self.mobius[n as usize]
+    }
+
+    /// Euler's φ(n), the count of numbers in [1, n] coprime to n.
+    pub fn phi(&self, n: u32) -> u32 {
+        self.phi[n as usize]
+    }
+
+    /// Factorizes 0 < n <= limit into `(prime, exponent)` pairs by following the smallest prime factors.
+    pub fn factorize(&self, mut n: u32) -> Vec<(u64, u32)> {
+        assert!(n > 0, "0 has no prime factorization");
+        let mut factors: Vec<(u64, u32)> = Vec::new();
+        while n > 1 {
+            let p = self.smallest_prime_factor(n);
+            let mut exponent: u32 = 0;
+            while n.is_multiple_of(p) {
+                n /= p;
+                exponent += 1;
+            }
+            factors.push((p as u64, exponent));
+        }
+        factors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::factorization::{euler_phi, Factorizer};
+    use crate::primes_sieve_of_eratosthenes;
+
+    #[test]
+    fn primes_agree_with_the_classic_sieve() {
+        for limit in [0, 1, 2, 3, 10, 1_000, 1_000_000] {
+            assert_eq!(LinearSieve::new(limit).primes(), primes_sieve_of_eratosthenes(limit), "limit {}", limit);
+        }
+    }
+
+    #[test]
+    fn tables_agree_with_factorizations() {
+        let sieve = LinearSieve::new(100_000);
+        let factorizer = Factorizer::default();
+        for n in 1..=100_000u32 {
+            let factors = factorizer.factorize(n as u64);
+            assert_eq!(sieve.factorize(n), factors, "n = {}", n);
+            assert_eq!(sieve.smallest_prime_factor(n) as u64, factors.first().map_or(0, |&(p, _)| p), "n = {}", n);
+            assert_eq!(sieve.phi(n) as u64, euler_phi(&factors), "n = {}", n);
+            let mobius = if factors.iter().any(|&(_, e)| e > 1) {
+                0
+            } else if factors.len() % 2 == 0 {
+                1
+            } else {
+                -1
+            };
+            assert_eq!(sieve.mobius(n), mobius, "n = {}", n);
+        }
+    }
+
+    #[test]
+    fn small_values() {
+        let sieve = LinearSieve::new(12);
+        let mobius: Vec<i8> = (0..=12).map(|n| sieve.mobius(n)).collect();
+        assert_eq!(mobius, vec![0, 1, -1, -1, 0, -1, 1, -1, 0, 0, 1, -1, 0]);
+        let phi: Vec<u32> = (0..=12).map(|n| sieve.phi(n)).collect();
+        assert_eq!(phi, vec![0, 1, 1, 2, 2, 4, 2, 6, 4, 6, 4, 10, 4]);
+        assert_eq!(sieve.smallest_prime_factor(1), 0);
+        assert_eq!(sieve.limit(), 12);
+    }
+}
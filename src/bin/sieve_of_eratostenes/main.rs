@@ -0,0 +1,379 @@
+use std::env;
+use std::io::{self, BufWriter, ErrorKind, Write};
+use std::ops::ControlFlow;
+use std::process::exit;
+
+use cli::{Command, Mode, Options};
+use error::SieveError;
+use goldbach::Goldbach;
+use linear_sieve::LinearSieve;
+use output::{GoldbachWriter, ListWriter, TableWriter};
+use prime_table::PrimeTable;
+use stats::PrimeStats;
+use unsigned::Unsigned;
+
+const FIRST_PRIME: usize = 2;
+
+/// All primes up to and including `upper_limit`, of the same type as the limit:
+/// `primes_sieve_of_eratosthenes(1_000_000u32)` gives a `Vec<u32>`.
+///
+/// Panics for `upper_limit == usize::MAX`, the sieve would need one more element than a Vec can hold.
+fn primes_sieve_of_eratosthenes<T: Unsigned>(upper_limit: T) -> Vec<T> {
+    let upper_limit = upper_limit.to_usize();
+    let size = upper_limit.checked_add(1).expect("a sieve up to usize::MAX does not fit into memory");
+    let mut sieve: Vec<bool> = vec![true; size];
+
+    // every composite number up to the limit has a prime factor <= √limit,
+    // so there is no need to sieve with larger primes
+    let last_sieving_number = upper_limit.isqrt();
+
+    let mut continue_number: Option<usize> = Some(FIRST_PRIME);
+
+    while let Some(p) = continue_number {
+        run_sieve_iteration(&mut sieve, p, upper_limit);
+        continue_number = find_smallest_not_marked_number(&sieve, p + 1, last_sieving_number);
+    }
+
+    resolve_numbers(&sieve)
+}
+
+// both limits are inclusive
+fn find_smallest_not_marked_number(sieve: &[bool], lower_limit: usize, upper_limit: usize) -> Option<usize> {
+    (lower_limit..=upper_limit).find(|&i| sieve[i])
+}
+
+// marks the multiples of p in place, starting at p²:
+// smaller multiples have a smaller prime factor and are already marked
+fn run_sieve_iteration(sieve: &mut [bool], p: usize, upper_limit: usize) {
+    for x in (p * p..=upper_limit).step_by(p) {
+        sieve[x] = false;
+    }
+}
+
+// the primes are at most the limit, so they fit into T
+fn resolve_numbers<T: Unsigned>(sieve: &[bool]) -> Vec<T> {
+    let mut primes: Vec<T> = Vec::new();
+    for (i, &is_prime) in sieve.iter().enumerate().skip(FIRST_PRIME) {
+        if is_prime {
+            primes.push(T::from_usize(i));
+        }
+    }
+    primes
+}
+
+
+mod algorithms;
+mod cli;
+mod error;
+mod factorization;
+mod goldbach;
+mod linear_sieve;
+mod output;
+mod parallel;
+mod primality;
+mod prime_table;
+mod primes;
+mod prime_count;
+mod prime_sieve;
+mod segmented;
+mod stats;
+mod unsigned;
+
+// feeds the primes of the range to f in ascending order, until f breaks;
+// they come from the imported table if there is one
+fn for_each_prime<B>(options: &Options, table: Option<&PrimeTable>, f: impl FnMut(u64) -> ControlFlow<B>) -> ControlFlow<B> {
+    if let Some(table) = table {
+        table.primes_in_range(options.from, options.limit).iter().copied().try_for_each(f)
+    } else if let Some(name) = options.algorithm {
+        // the limit was checked to fit into usize when parsing the arguments
+        let sieve = algorithms::by_name(name).expect("algorithm names are checked when parsing the arguments");
+        sieve.primes_up_to(options.limit as usize).into_iter()
+            .map(|p| p as u64)
+            .filter(|&p| p >= options.from)
+            .try_for_each(f)
+    } else if options.threads > 1 {
+        parallel::try_for_each_prime_in_range_parallel(options.from, options.limit, options.threads, f)
+    } else {
+        segmented::try_for_each_prime_in_range(options.from, options.limit, f)
+    }
+}
+
+fn run(options: &Options, out: &mut impl Write) -> Result<(), SieveError> {
+    let (from, to, format) = (options.from, options.limit, options.format);
+    let table = match &options.import {
+        Some(path) => {
+            let table = prime_table::load(path)?;
+            if !table.covers(from, to) {
+                return Err(SieveError::Usage(format!("the table in {} only covers {} to {}",
+                                                     path.display(), table.from, table.limit)));
+            }
+            Some(table)
+        }
+        None => None,
+    };
+    let table = table.as_ref();
+
+    match (options.mode, &options.export) {
+        (Mode::List, Some((path, table_format))) => {
+            let mut writer = prime_table::TableWriter::create(path, *table_format, from, to)?;
+            if let ControlFlow::Break(e) = for_each_prime(options, table, |p| match writer.write(p) {
+                Ok(()) => ControlFlow::Continue(()),
+                Err(e) => ControlFlow::Break(e),
+            }) {
+                return Err(SieveError::Io(e));
+            }
+            writer.finish()?;
+        }
+        (Mode::List, None) => {
+            let mut writer = ListWriter::start(out, format, from, to)?;
+            if let ControlFlow::Break(e) = for_each_prime(options, table, |p| match writer.write(p) {
+                Ok(()) => ControlFlow::Continue(()),
+                Err(e) => ControlFlow::Break(e),
+            }) {
+                return Err(SieveError::Io(e));
+            }
+            writer.finish()?;
+        }
+        (Mode::Count, _) => {
+            // counting does not need to look at every prime
+            let count = match (table, options.algorithm) {
+                (Some(table), _) => table.primes_in_range(from, to).len() as u64,
+                (None, Some(_)) => {
+                    let mut count: u64 = 0;
+                    let _: ControlFlow<()> = for_each_prime(options, None, |_| {
+                        count += 1;
+                        ControlFlow::Continue(())
+                    });
+                    count
+                }
+                (None, None) => prime_count::count_primes_in_range(from, to),
+            };
+            output::write_count(out, format, from, to, count)?;
+        }
+        (Mode::Nth(n), _) => {
+            let mut count: u64 = 0;
+            let found = for_each_prime(options, table, |p| {
+                count += 1;
+                if count == n { ControlFlow::Break(p) } else { ControlFlow::Continue(()) }
+            });
+            match found {
+                ControlFlow::Break(p) => output::write_nth(out, format, from, to, n, p)?,
+                ControlFlow::Continue(()) => {
+                    return Err(SieveError::NotFound(format!("there are only {} primes between {} and {}", count, from, to)));
+                }
+            }
+        }
+        (Mode::Last, _) => {
+            let last = match (table, options.algorithm) {
+                // the answer lies in the last segments, its position comes from counting
+                (None, None) => segmented::last_prime_in_range(from, to)
+                    .map(|p| (prime_count::count_primes_in_range(from, p), p)),
+                _ => {
+                    let mut count: u64 = 0;
+                    let mut last: Option<u64> = None;
+                    let _: ControlFlow<()> = for_each_prime(options, table, |p| {
+                        count += 1;
+                        last = Some(p);
+                        ControlFlow::Continue(())
+                    });
+                    last.map(|p| (count, p))
+                }
+            };
+            match last {
+                Some((count, p)) => output::write_nth(out, format, from, to, count, p)?,
+                None => return Err(SieveError::NotFound(format!("there is no prime between {} and {}", from, to))),
+            }
+        }
+        (Mode::Goldbach, _) => {
+            let goldbach = Goldbach::new(to);
+            let mut writer = GoldbachWriter::start(out, format, from, to)?;
+            for (n, count) in goldbach.partition_counts(from, to) {
+                match goldbach.minimal_partition(n) {
+                    Some(minimal) => writer.write(n, count, minimal)?,
+                    None => return Err(SieveError::NotFound(format!("{} is not the sum of two primes", n))),
+                }
+            }
+            writer.finish()?;
+        }
+        (Mode::GoldbachVerify, _) => {
+            let verification = Goldbach::new(to).verify(from, to);
+            if let Some(n) = verification.counterexample {
+                return Err(SieveError::NotFound(format!("{} is not the sum of two primes", n)));
+            }
+            output::write_goldbach_verification(out, format, from, to, &verification)?;
+        }
+        (Mode::Stats, _) => {
+            let mut stats = PrimeStats::new();
+            let _: ControlFlow<()> = for_each_prime(options, table, |p| {
+                stats.add(p);
+                ControlFlow::Continue(())
+            });
+            output::write_stats(out, format, from, to, &stats)?;
+        }
+        (Mode::Table, _) => {
+            // the limit was checked to fit into u32 when parsing the arguments. The linear sieve always
+            // covers [0, to], whatever the start of the range, so its memory depends on the limit alone.
+            let sieve = LinearSieve::new(to as u32);
+            let mut writer = TableWriter::start(out, format, from, to)?;
+            // 0 has neither a prime factor nor μ and φ
+            for n in from.max(1)..=to {
+                let n = n as u32;
+                writer.write(n, sieve.smallest_prime_factor(n), sieve.mobius(n), sieve.phi(n), &sieve.factorize(n))?;
+            }
+            writer.finish()?;
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let result = match cli::parse_args(&args) {
+        Ok(Command::Help) => {
+            cli::print_usage(&args[0]);
+            Ok(())
+        }
+        Ok(Command::Sieve(options)) => run(&options, &mut BufWriter::new(io::stdout().lock())),
+        Ok(Command::IsPrime { n, format }) => {
+            output::write_primality(&mut io::stdout().lock(), format, n, primality::primality(n)).map_err(SieveError::Io)
+        }
+        Ok(Command::Factor { n, format }) => {
+            let factors = factorization::Factorizer::default().factorize(n);
+            output::write_factorization(&mut io::stdout().lock(), format, n, &factors).map_err(SieveError::Io)
+        }
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(()) => {}
+        // the reading end went away, e.g. when piping into `head`
+        Err(SieveError::Io(e)) if e.kind() == ErrorKind::BrokenPipe => {}
+        Err(e) => {
+            eprintln!("error: {}", e);
+            if let SieveError::Usage(_) = e {
+                eprintln!("try '{} --help'", args[0]);
+            }
+            exit(e.exit_code())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    // the sieve as it was before marking in place: it collected all multiples of p from 2p on
+    // into a fresh Vec, and did so for every prime up to the limit instead of up to √limit
+    fn primes_sieve_collecting_multiples(upper_limit: usize) -> Vec<usize> {
+        let mut sieve: Vec<bool> = vec![true; upper_limit + 1];
+        let mut continue_number: Option<usize> = Some(FIRST_PRIME);
+        while let Some(p) = continue_number {
+            let multiples: Vec<usize> = (2 * p..=upper_limit).step_by(p).collect();
+            for x in multiples {
+                sieve[x] = false;
+            }
+            continue_number = find_smallest_not_marked_number(&sieve, p + 1, upper_limit);
+        }
+        resolve_numbers(&sieve)
+    }
+
+    fn timed(f: impl FnOnce() -> Vec<usize>) -> (Vec<usize>, Duration) {
+        let start = Instant::now();
+        let result = f();
+        (result, start.elapsed())
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with: cargo test --release --bin sieve_of_eratostenes -- --ignored"]
+    fn in_place_marking_is_faster_at_10_pow_8() {
+        let limit = 100_000_000;
+        let (before, before_time) = timed(|| primes_sieve_collecting_multiples(limit));
+        let (after, after_time) = timed(|| primes_sieve_of_eratosthenes(limit));
+        println!("sieve up to {}: collecting multiples {:?}, marking in place {:?}", limit, before_time, after_time);
+
+        assert_eq!(after.len(), 5_761_455);
+        assert_eq!(after, before);
+        assert!(after_time * 2 < before_time, "expected at least a 2x speedup");
+    }
+
+    fn is_prime_by_trial_division(n: usize) -> bool {
+        n >= 2 && (2..).take_while(|d| d * d <= n).all(|d| !n.is_multiple_of(d))
+    }
+
+    // xorshift64*, good enough to pick test limits without pulling in a crate
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        }
+
+        fn below(&mut self, bound: usize) -> usize {
+            (self.next() % bound as u64) as usize
+        }
+    }
+
+    const MAX_TESTED_LIMIT: usize = 200_000;
+
+    fn check_against_trial_division(limit: usize, reference: &[usize]) {
+        let expected: Vec<usize> = reference.iter().copied().take_while(|&p| p <= limit).collect();
+        assert_eq!(primes_sieve_of_eratosthenes(limit), expected, "limit {}", limit);
+    }
+
+    #[test]
+    fn edge_case_limits() {
+        let reference: Vec<usize> = (0..=MAX_TESTED_LIMIT).filter(|&n| is_prime_by_trial_division(n)).collect();
+        let mut limits: Vec<usize> = vec![0, 1, 2, 3, 4, 5, 6, 7, 8];
+        // the limit itself as the last sieving prime or just around it
+        for &p in reference.iter().take_while(|&&p| p * p <= MAX_TESTED_LIMIT) {
+            limits.extend([p, p * p - 1, p * p, p * p + 1]);
+        }
+        limits.extend(reference.iter().rev().take(5));
+        for limit in limits {
+            check_against_trial_division(limit, &reference);
+        }
+    }
+
+    #[test]
+    fn random_limits() {
+        let reference: Vec<usize> = (0..=MAX_TESTED_LIMIT).filter(|&n| is_prime_by_trial_division(n)).collect();
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..200 {
+            // mostly small limits, where the boundaries matter most
+            let bound = if rng.below(4) == 0 { MAX_TESTED_LIMIT + 1 } else { 1_000 };
+            check_against_trial_division(rng.below(bound), &reference);
+        }
+    }
+
+    #[test]
+    fn find_smallest_not_marked_number_includes_both_limits() {
+        let sieve = [false, false, true, false, false, true];
+        assert_eq!(find_smallest_not_marked_number(&sieve, 2, 2), Some(2));
+        assert_eq!(find_smallest_not_marked_number(&sieve, 3, 5), Some(5));
+        assert_eq!(find_smallest_not_marked_number(&sieve, 3, 4), None);
+        assert_eq!(find_smallest_not_marked_number(&sieve, 5, 4), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "a sieve up to usize::MAX does not fit into memory")]
+    fn limit_usize_max_panics_instead_of_overflowing() {
+        primes_sieve_of_eratosthenes(usize::MAX);
+    }
+
+    #[test]
+    fn generic_over_the_integer_width() {
+        let wide: Vec<usize> = primes_sieve_of_eratosthenes(100_000usize);
+        let as_u64: Vec<u64> = primes_sieve_of_eratosthenes(100_000u64);
+        let as_u32: Vec<u32> = primes_sieve_of_eratosthenes(100_000u32);
+        assert_eq!(as_u64, wide.iter().map(|&p| p as u64).collect::<Vec<u64>>());
+        assert_eq!(as_u32, wide.iter().map(|&p| p as u32).collect::<Vec<u32>>());
+        // the whole range of the type
+        let as_u8: Vec<u8> = primes_sieve_of_eratosthenes(u8::MAX);
+        assert_eq!(as_u8.last(), Some(&251));
+        assert_eq!(primes_sieve_of_eratosthenes(u16::MAX).len(), 6_542);
+    }
+}
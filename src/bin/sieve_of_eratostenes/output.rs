@@ -0,0 +1,313 @@
+use std::io::{self, Write};
+
+use crate::cli::Format;
+use crate::goldbach::Verification;
+use crate::factorization::{divisor_count, divisor_sum, euler_phi};
+use crate::primality::Primality;
+use crate::stats::PrimeStats;
+
+/// Writes a list of primes as they come in, without collecting them first.
+pub struct ListWriter<'a, W: Write> {
+    out: &'a mut W,
+    format: Format,
+    count: u64,
+}
+
+impl<'a, W: Write> ListWriter<'a, W> {
+    pub fn start(out: &'a mut W, format: Format, from: u64, to: u64) -> io::Result<ListWriter<'a, W>> {
+        match format {
+            Format::Plain => {}
+            Format::Csv => writeln!(out, "n,prime")?,
+            Format::Json => write!(out, "{{\"from\": {}, \"to\": {}, \"primes\": [", from, to)?,
+        }
+        Ok(ListWriter { out, format, count: 0 })
+    }
+
+    pub fn write(&mut self, prime: u64) -> io::Result<()> {
+        self.count += 1;
+        match self.format {
+            Format::Plain => writeln!(self.out, "{}", prime),
+            Format::Csv => writeln!(self.out, "{},{}", self.count, prime),
+            Format::Json if self.count == 1 => write!(self.out, "\n  {}", prime),
+            Format::Json => write!(self.out, ",\n  {}", prime),
+        }
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        if self.format == Format::Json {
+            writeln!(self.out, "\n]}}")?;
+        }
+        self.out.flush()
+    }
+}
+
+/// Writes rows of the linear sieve tables as they come in.
+pub struct TableWriter<'a, W: Write> {
+    out: &'a mut W,
+    format: Format,
+    count: u64,
+}
+
+impl<'a, W: Write> TableWriter<'a, W> {
+    pub fn start(out: &'a mut W, format: Format, from: u64, to: u64) -> io::Result<TableWriter<'a, W>> {
+        match format {
+            Format::Plain => {}
+            Format::Csv => writeln!(out, "n,spf,mu,phi,factorization")?,
+            Format::Json => write!(out, "{{\"from\": {}, \"to\": {}, \"rows\": [", from, to)?,
+        }
+        Ok(TableWriter { out, format, count: 0 })
+    }
+
+    pub fn write(&mut self, n: u32, smallest_prime_factor: u32, mobius: i8, phi: u32, factors: &[(u64, u32)]) -> io::Result<()> {
+        self.count += 1;
+        let separator = if self.count == 1 { "" } else { "," };
+        match self.format {
+            Format::Plain => writeln!(self.out, "{} {} {} {} {}", n, smallest_prime_factor, mobius, phi, product(factors, "*")),
+            Format::Csv => writeln!(self.out, "{},{},{},{},{}", n, smallest_prime_factor, mobius, phi, product(factors, "*")),
+            Format::Json => write!(self.out, "{}\n  {{\"n\": {}, \"spf\": {}, \"mu\": {}, \"phi\": {}, \"factors\": [{}]}}",
+                                   separator, n, smallest_prime_factor, mobius, phi, json_factors(factors)),
+        }
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        if self.format == Format::Json {
+            writeln!(self.out, "\n]}}")?;
+        }
+        self.out.flush()
+    }
+}
+
+/// Writes the Goldbach partitions of the even numbers of a range as they come in.
+pub struct GoldbachWriter<'a, W: Write> {
+    out: &'a mut W,
+    format: Format,
+    count: u64,
+}
+
+impl<'a, W: Write> GoldbachWriter<'a, W> {
+    pub fn start(out: &'a mut W, format: Format, from: u64, to: u64) -> io::Result<GoldbachWriter<'a, W>> {
+        match format {
+            Format::Plain => {}
+            Format::Csv => writeln!(out, "n,partitions,p,q")?,
+            Format::Json => write!(out, "{{\"from\": {}, \"to\": {}, \"partitions\": [", from, to)?,
+        }
+        Ok(GoldbachWriter { out, format, count: 0 })
+    }
+
+    /// `(p, q)` is the partition n = p + q with the smallest p.
+    pub fn write(&mut self, n: u64, partitions: u64, (p, q): (u64, u64)) -> io::Result<()> {
+        self.count += 1;
+        let separator = if self.count == 1 { "" } else { "," };
+        match self.format {
+            Format::Plain => writeln!(self.out, "{} = {} + {}, {} partitions", n, p, q, partitions),
+            Format::Csv => writeln!(self.out, "{},{},{},{}", n, partitions, p, q),
+            Format::Json => write!(self.out, "{}\n  {{\"n\": {}, \"count\": {}, \"p\": {}, \"q\": {}}}",
+                                   separator, n, partitions, p, q),
+        }
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        if self.format == Format::Json {
+            writeln!(self.out, "\n]}}")?;
+        }
+        self.out.flush()
+    }
+}
+
+pub fn write_count(out: &mut impl Write, format: Format, from: u64, to: u64, count: u64) -> io::Result<()> {
+    match format {
+        Format::Plain => writeln!(out, "{}", count)?,
+        Format::Csv => writeln!(out, "from,to,count\n{},{},{}", from, to, count)?,
+        Format::Json => writeln!(out, "{{\"from\": {}, \"to\": {}, \"count\": {}}}", from, to, count)?,
+    }
+    out.flush()
+}
+
+/// Writes a single prime together with its position n within the range.
+pub fn write_nth(out: &mut impl Write, format: Format, from: u64, to: u64, n: u64, prime: u64) -> io::Result<()> {
+    match format {
+        Format::Plain => writeln!(out, "{}", prime)?,
+        Format::Csv => writeln!(out, "from,to,n,prime\n{},{},{},{}", from, to, n, prime)?,
+        Format::Json => writeln!(out, "{{\"from\": {}, \"to\": {}, \"n\": {}, \"prime\": {}}}", from, to, n, prime)?,
+    }
+    out.flush()
+}
+
+pub fn write_primality(out: &mut impl Write, format: Format, n: u128, primality: Primality) -> io::Result<()> {
+    let (verdict, name) = match primality {
+        Primality::Prime => ("is prime", "prime"),
+        Primality::ProbablyPrime => ("is probably prime", "probably prime"),
+        Primality::NotPrime => ("is not prime", "not prime"),
+    };
+    match format {
+        Format::Plain => writeln!(out, "{} {}", n, verdict)?,
+        Format::Csv => writeln!(out, "n,primality\n{},{}", n, name)?,
+        Format::Json => writeln!(out, "{{\"n\": {}, \"primality\": \"{}\"}}", n, name)?,
+    }
+    out.flush()
+}
+
+/// Writes n with its factorization as `(prime, exponent)` pairs and the divisor functions derived from it.
+// p^e factors joined by the separator, "1" for no factors
+fn product(factors: &[(u64, u32)], separator: &str) -> String {
+    if factors.is_empty() {
+        return String::from("1");
+    }
+    factors.iter()
+        .map(|&(p, e)| if e == 1 { p.to_string() } else { format!("{}^{}", p, e) })
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+fn json_factors(factors: &[(u64, u32)]) -> String {
+    factors.iter().map(|(p, e)| format!("[{}, {}]", p, e)).collect::<Vec<String>>().join(", ")
+}
+
+pub fn write_factorization(out: &mut impl Write, format: Format, n: u64, factors: &[(u64, u32)]) -> io::Result<()> {
+    let (divisors, sigma, phi) = (divisor_count(factors), divisor_sum(factors), euler_phi(factors));
+    let product = product(factors, " * ");
+    match format {
+        Format::Plain => {
+            writeln!(out, "{} = {}", n, product)?;
+            writeln!(out, "divisors: {}\nsigma: {}\nphi: {}", divisors, sigma, phi)?;
+        }
+        Format::Csv => {
+            writeln!(out, "n,factorization,divisors,sigma,phi")?;
+            writeln!(out, "{},{},{},{},{}", n, product.replace(' ', ""), divisors, sigma, phi)?;
+        }
+        Format::Json => {
+            writeln!(out, "{{\"n\": {}, \"factors\": [{}], \"divisors\": {}, \"sigma\": {}, \"phi\": {}}}",
+                     n, json_factors(factors), divisors, sigma, phi)?;
+        }
+    }
+    out.flush()
+}
+
+fn json_pairs(pairs: impl Iterator<Item=(u64, u64)>) -> String {
+    pairs.map(|(gap, after)| format!("{{\"gap\": {}, \"after\": {}}}", gap, after)).collect::<Vec<String>>().join(", ")
+}
+
+pub fn write_stats(out: &mut impl Write, format: Format, from: u64, to: u64, stats: &PrimeStats) -> io::Result<()> {
+    let constellations = [
+        ("twin pairs (p, p+2)", "twins", stats.twins),
+        ("cousin pairs (p, p+4)", "cousins", stats.cousins),
+        ("sexy pairs (p, p+6)", "sexy", stats.sexy),
+        ("triplets (p, ..., p+6)", "triplets", stats.triplets),
+        ("quadruplets (p, p+2, p+6, p+8)", "quadruplets", stats.quadruplets),
+        ("quintuplets (p, ..., p+12)", "quintuplets", stats.quintuplets),
+    ];
+    match format {
+        Format::Plain => {
+            writeln!(out, "primes between {} and {}: {}", from, to, stats.count)?;
+            writeln!(out, "maximal gaps:")?;
+            for (gap, after) in stats.maximal_gaps.iter() {
+                writeln!(out, "  {:>6} after {}", gap, after)?;
+            }
+            writeln!(out, "first occurrence of each gap:")?;
+            for (gap, after) in stats.first_gaps.iter() {
+                writeln!(out, "  {:>6} after {}", gap, after)?;
+            }
+            for (label, _, count) in constellations {
+                writeln!(out, "{}: {}", label, count)?;
+            }
+            writeln!(out, "Chebyshev bias:")?;
+            for race in stats.races.iter() {
+                let m = race.modulus;
+                writeln!(out, "  mod {}: {} primes = 1, {} primes = {}, the latter ahead after {} of {} primes",
+                         m, race.counts[1], race.counts[m as usize - 1], m - 1, race.leads, stats.count)?;
+            }
+        }
+        Format::Csv => {
+            writeln!(out, "statistic,key,value")?;
+            writeln!(out, "count,,{}", stats.count)?;
+            for (gap, after) in stats.maximal_gaps.iter() {
+                writeln!(out, "maximal_gap,{},{}", gap, after)?;
+            }
+            for (gap, after) in stats.first_gaps.iter() {
+                writeln!(out, "first_gap,{},{}", gap, after)?;
+            }
+            for (_, key, count) in constellations {
+                writeln!(out, "{},,{}", key, count)?;
+            }
+            for race in stats.races.iter() {
+                for (residue, count) in race.counts.iter().enumerate().filter(|&(residue, _)| residue == 1 || residue == race.counts.len() - 1) {
+                    writeln!(out, "residue_mod_{},{},{}", race.modulus, residue, count)?;
+                }
+                writeln!(out, "leads_mod_{},{},{}", race.modulus, race.modulus - 1, race.leads)?;
+            }
+        }
+        Format::Json => {
+            writeln!(out, "{{\"from\": {}, \"to\": {}, \"count\": {},", from, to, stats.count)?;
+            writeln!(out, "  \"maximal_gaps\": [{}],", json_pairs(stats.maximal_gaps.iter().copied()))?;
+            writeln!(out, "  \"first_gaps\": [{}],", json_pairs(stats.first_gaps.iter().map(|(&gap, &after)| (gap, after))))?;
+            for (_, key, count) in constellations {
+                writeln!(out, "  \"{}\": {},", key, count)?;
+            }
+            let races: Vec<String> = stats.races.iter()
+                .map(|race| {
+                    let m = race.modulus;
+                    format!("{{\"modulus\": {}, \"residue_1\": {}, \"residue_{}\": {}, \"leads\": {}}}",
+                            m, race.counts[1], m - 1, race.counts[m as usize - 1], race.leads)
+                })
+                .collect();
+            writeln!(out, "  \"chebyshev_bias\": [{}]}}", races.join(", "))?;
+        }
+    }
+    out.flush()
+}
+
+pub fn write_goldbach_verification(out: &mut impl Write, format: Format, from: u64, to: u64,
+                                   verification: &Verification) -> io::Result<()> {
+    let (hardest_n, hardest_p) = verification.hardest.unwrap_or((0, 0));
+    match format {
+        Format::Plain => {
+            writeln!(out, "every even number between {} and {} is the sum of two primes ({} checked)",
+                     from, to, verification.checked)?;
+            if verification.hardest.is_some() {
+                writeln!(out, "the largest smallest prime needed is {}, for {}", hardest_p, hardest_n)?;
+            }
+        }
+        Format::Csv => writeln!(out, "from,to,checked,hardest_n,hardest_p\n{},{},{},{},{}",
+                                from, to, verification.checked, hardest_n, hardest_p)?,
+        Format::Json => writeln!(out, "{{\"from\": {}, \"to\": {}, \"checked\": {}, \"hardest_n\": {}, \"hardest_p\": {}}}",
+                                 from, to, verification.checked, hardest_n, hardest_p)?,
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listed(format: Format, primes: &[u64]) -> String {
+        let mut out: Vec<u8> = Vec::new();
+        let mut writer = ListWriter::start(&mut out, format, 0, 10).unwrap();
+        for &p in primes {
+            writer.write(p).unwrap();
+        }
+        writer.finish().unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn plain_list() {
+        assert_eq!(listed(Format::Plain, &[]), "");
+        assert_eq!(listed(Format::Plain, &[2]), "2\n");
+        assert_eq!(listed(Format::Plain, &[2, 3, 5, 7]), "2\n3\n5\n7\n");
+    }
+
+    #[test]
+    fn csv_list() {
+        assert_eq!(listed(Format::Csv, &[]), "n,prime\n");
+        assert_eq!(listed(Format::Csv, &[2]), "n,prime\n1,2\n");
+        assert_eq!(listed(Format::Csv, &[2, 3, 5, 7]), "n,prime\n1,2\n2,3\n3,5\n4,7\n");
+    }
+
+    #[test]
+    fn json_list() {
+        assert_eq!(listed(Format::Json, &[]), "{\"from\": 0, \"to\": 10, \"primes\": [\n]}\n");
+        assert_eq!(listed(Format::Json, &[2]), "{\"from\": 0, \"to\": 10, \"primes\": [\n  2\n]}\n");
+        assert_eq!(listed(Format::Json, &[2, 3, 5, 7]),
+                   "{\"from\": 0, \"to\": 10, \"primes\": [\n  2,\n  3,\n  5,\n  7\n]}\n");
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+use std::ops::ControlFlow;
+use std::sync::{Condvar, Mutex};
+use std::thread;
+
+use crate::segmented::{base_primes, sieve_range, SEGMENT_SIZE};
+
+// numbers per chunk, the unit of work a thread takes at a time
+const CHUNK_SIZE: u64 = 4 * SEGMENT_SIZE as u64;
+
+// chunks per thread which may be sieved ahead of the one handed to f next,
+// so threads finishing early keep working while the memory stays bounded
+const CHUNKS_AHEAD_PER_THREAD: usize = 2;
+
+// shared between the workers and the thread handing the primes to f
+struct Progress {
+    // the next chunk a worker may take
+    next_chunk: usize,
+    // the next chunk to be handed to f
+    next_delivered: usize,
+    // primes of the chunks sieved but not handed to f yet
+    sieved: BTreeMap<usize, Vec<u64>>,
+    // workers which have not finished yet
+    running: usize,
+    // set once f breaks or anyone panics, so the workers stop taking chunks and nobody waits any more
+    stopped: bool,
+}
+
+// counts a worker out when it ends. A worker that panics never delivers its chunk, so it also stops
+// the others and wakes the thread calling f, which would otherwise wait for that chunk forever.
+struct WorkerGuard<'a> {
+    progress: &'a Mutex<Progress>,
+    changed: &'a Condvar,
+}
+
+impl Drop for WorkerGuard<'_> {
+    fn drop(&mut self) {
+        let mut progress = self.progress.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        progress.running -= 1;
+        if thread::panicking() {
+            progress.stopped = true;
+        }
+        self.changed.notify_all();
+    }
+}
+
+// stops the workers when the thread calling f is done, also when f panics; the scope waits for the
+// workers before it passes the panic on, and they must not wait for room in the window meanwhile
+struct ConsumerGuard<'a> {
+    progress: &'a Mutex<Progress>,
+    changed: &'a Condvar,
+}
+
+impl Drop for ConsumerGuard<'_> {
+    fn drop(&mut self) {
+        self.progress.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).stopped = true;
+        self.changed.notify_all();
+    }
+}
+
+/// Calls `f` with every prime in [lo, hi] (both inclusive) in ascending order, sieved by `threads`
+/// worker threads; stops as soon as `f` returns `ControlFlow::Break`.
+///
+/// The range is cut into chunks of whole segments which the workers take in order. Each chunk's
+/// primes are handed to `f` as soon as all chunks before it are through, and the workers stay at
+/// most a few chunks per thread ahead, so the memory does not grow with the range.
+pub fn try_for_each_prime_in_range_parallel<B>(lo: u64, hi: u64, threads: usize,
+                                               mut f: impl FnMut(u64) -> ControlFlow<B>) -> ControlFlow<B> {
+    assert!(threads > 0, "at least one thread is needed");
+    if lo > hi {
+        return ControlFlow::Continue(());
+    }
+
+    let base_primes = base_primes(hi.isqrt());
+    let chunks = ((hi - lo) / CHUNK_SIZE + 1) as usize;
+    let workers = threads.min(chunks);
+    let window = workers * CHUNKS_AHEAD_PER_THREAD;
+
+    let progress = Mutex::new(Progress {
+        next_chunk: 0,
+        next_delivered: 0,
+        sieved: BTreeMap::new(),
+        running: workers,
+        stopped: false,
+    });
+    let changed = Condvar::new();
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                let _guard = WorkerGuard { progress: &progress, changed: &changed };
+                loop {
+                    let chunk = {
+                        let mut state = changed.wait_while(progress.lock().unwrap(), |state| {
+                            !state.stopped && state.next_chunk < chunks
+                                && state.next_chunk >= state.next_delivered + window
+                        }).unwrap();
+                        if state.stopped || state.next_chunk >= chunks {
+                            return;
+                        }
+                        state.next_chunk += 1;
+                        state.next_chunk - 1
+                    };
+
+                    let chunk_lo = lo + chunk as u64 * CHUNK_SIZE;
+                    let chunk_hi = hi.min(chunk_lo.saturating_add(CHUNK_SIZE - 1));
+                    let mut primes: Vec<u64> = Vec::new();
+                    let _: ControlFlow<()> = sieve_range(chunk_lo, chunk_hi, &base_primes, |p| {
+                        primes.push(p);
+                        ControlFlow::Continue(())
+                    });
+
+                    progress.lock().unwrap().sieved.insert(chunk, primes);
+                    changed.notify_all();
+                }
+            });
+        }
+
+        let _guard = ConsumerGuard { progress: &progress, changed: &changed };
+        loop {
+            let primes = {
+                let mut state = changed.wait_while(progress.lock().unwrap(), |state| {
+                    !state.stopped && state.next_delivered < chunks && state.running > 0
+                        && !state.sieved.contains_key(&state.next_delivered)
+                }).unwrap();
+                if state.stopped {
+                    // a worker panicked, the scope passes its panic on
+                    break ControlFlow::Continue(());
+                }
+                let next = state.next_delivered;
+                match state.sieved.remove(&next) {
+                    Some(primes) => {
+                        state.next_delivered += 1;
+                        primes
+                    }
+                    // all chunks are through
+                    None => break ControlFlow::Continue(()),
+                }
+            };
+            // a worker may take the next chunk now
+            changed.notify_all();
+            if let ControlFlow::Break(b) = primes.into_iter().try_for_each(&mut f) {
+                break ControlFlow::Break(b);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segmented::primes_in_range;
+
+    fn primes_in_range_parallel(lo: u64, hi: u64, threads: usize) -> Vec<u64> {
+        let mut primes: Vec<u64> = Vec::new();
+        let _: ControlFlow<()> = try_for_each_prime_in_range_parallel(lo, hi, threads, |p| {
+            primes.push(p);
+            ControlFlow::Continue(())
+        });
+        primes
+    }
+
+    #[test]
+    fn chunks_come_in_order() {
+        // 13 chunks and a few more numbers, more chunks than fit into the window of 3 threads
+        let hi = 13 * CHUNK_SIZE + 12_345;
+        let expected = primes_in_range(0, hi);
+        for threads in [1, 2, 3, 4] {
+            assert_eq!(primes_in_range_parallel(0, hi, threads), expected, "{} threads", threads);
+        }
+    }
+
+    #[test]
+    fn ranges_not_made_of_whole_chunks() {
+        let lo = 1_000_000_000;
+        for hi in [lo + CHUNK_SIZE - 1, lo + CHUNK_SIZE, lo + CHUNK_SIZE + 1, lo + 2 * CHUNK_SIZE + 777] {
+            assert_eq!(primes_in_range_parallel(lo, hi, 3), primes_in_range(lo, hi), "[{}, {}]", lo, hi);
+        }
+    }
+
+    #[test]
+    fn more_threads_than_chunks() {
+        assert_eq!(primes_in_range_parallel(0, 100, 8), primes_in_range(0, 100));
+        assert_eq!(primes_in_range_parallel(7, 7, 8), vec![7]);
+        assert_eq!(primes_in_range_parallel(0, CHUNK_SIZE, 16), primes_in_range(0, CHUNK_SIZE));
+    }
+
+    #[test]
+    fn empty_ranges() {
+        assert_eq!(primes_in_range_parallel(10, 9, 4), Vec::<u64>::new());
+        assert_eq!(primes_in_range_parallel(u64::MAX, 0, 4), Vec::<u64>::new());
+        assert_eq!(primes_in_range_parallel(24, 28, 4), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn stops_when_f_breaks() {
+        // the 100,000th prime, found long before the end of the range
+        let mut count = 0;
+        let found = try_for_each_prime_in_range_parallel(0, 1 << 40, 4, |p| {
+            count += 1;
+            if count == 100_000 { ControlFlow::Break(p) } else { ControlFlow::Continue(()) }
+        });
+        assert_eq!(found, ControlFlow::Break(1_299_709));
+    }
+
+    #[test]
+    #[should_panic(expected = "f gives up")]
+    fn passes_a_panic_of_f_on() {
+        // the workers fill the window long before f panics, and must not keep waiting for room in it
+        let mut count = 0;
+        let _: ControlFlow<()> = try_for_each_prime_in_range_parallel(0, 1 << 40, 2, |_| {
+            count += 1;
+            if count == 1_000 { panic!("f gives up") }
+            ControlFlow::Continue(())
+        });
+    }
+}
@@ -0,0 +1,185 @@
+/// Primality of a single number, without sieving up to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Primality {
+    Prime,
+    /// passed the strong probable-prime test, but beyond the range of the deterministic test
+    ProbablyPrime,
+    NotPrime,
+}
+
+// the smallest number fooling Miller-Rabin with all these bases is 318665857834031151167461 > 2^64,
+// so the test is exact for u64
+const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+/// a * b mod m without overflow, widening to u128.
+pub fn mul_mod_u64(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+pub fn pow_mod_u64(mut base: u64, mut exponent: u64, m: u64) -> u64 {
+    let mut result: u64 = 1 % m;
+    base %= m;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod_u64(result, base, m);
+        }
+        base = mul_mod_u64(base, base, m);
+        exponent >>= 1;
+    }
+    result
+}
+
+// a + b mod m for a, b < m, without overflowing u128
+fn add_mod_u128(a: u128, b: u128, m: u128) -> u128 {
+    if a >= m - b { a - (m - b) } else { a + b }
+}
+
+/// a * b mod m without overflow. There is no wider type than u128,
+/// so this adds up the shifted a for every set bit of b.
+pub fn mul_mod_u128(a: u128, b: u128, m: u128) -> u128 {
+    let (mut a, mut b) = (a % m, b % m);
+    let mut result: u128 = 0;
+    while b > 0 {
+        if b & 1 == 1 {
+            result = add_mod_u128(result, a, m);
+        }
+        a = add_mod_u128(a, a, m);
+        b >>= 1;
+    }
+    result
+}
+
+pub fn pow_mod_u128(mut base: u128, mut exponent: u128, m: u128) -> u128 {
+    let mut result: u128 = 1 % m;
+    base %= m;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod_u128(result, base, m);
+        }
+        base = mul_mod_u128(base, base, m);
+        exponent >>= 1;
+    }
+    result
+}
+
+// n is odd and > 1; true if n is a strong probable prime to base a,
+// given n - 1 = d * 2^s with d odd
+fn is_strong_probable_prime_u64(n: u64, d: u64, s: u32, a: u64) -> bool {
+    let mut x = pow_mod_u64(a, d, n);
+    if x == 1 || x == n - 1 {
+        return true;
+    }
+    for _ in 1..s {
+        x = mul_mod_u64(x, x, n);
+        if x == n - 1 {
+            return true;
+        }
+    }
+    false
+}
+
+fn is_strong_probable_prime_u128(n: u128, d: u128, s: u32, a: u128) -> bool {
+    let mut x = pow_mod_u128(a, d, n);
+    if x == 1 || x == n - 1 {
+        return true;
+    }
+    for _ in 1..s {
+        x = mul_mod_u128(x, x, n);
+        if x == n - 1 {
+            return true;
+        }
+    }
+    false
+}
+
+/// Deterministic Miller-Rabin test, exact for every u64.
+pub fn is_prime_u64(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    // the witnesses double as trial divisors, which also settles n <= 37
+    for &p in WITNESSES.iter() {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    WITNESSES.iter().all(|&a| is_strong_probable_prime_u64(n, d, s, a))
+}
+
+/// Exact for n <= u64::MAX; beyond that a strong probable-prime test to the first twelve prime bases.
+pub fn primality(n: u128) -> Primality {
+    if let Ok(n) = u64::try_from(n) {
+        return if is_prime_u64(n) { Primality::Prime } else { Primality::NotPrime };
+    }
+    for &p in WITNESSES.iter() {
+        if n.is_multiple_of(p as u128) {
+            return Primality::NotPrime;
+        }
+    }
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    if WITNESSES.iter().all(|&a| is_strong_probable_prime_u128(n, d, s, a as u128)) {
+        Primality::ProbablyPrime
+    } else {
+        Primality::NotPrime
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primes_sieve_of_eratosthenes;
+
+    #[test]
+    fn agrees_with_the_sieve_for_small_numbers() {
+        let limit = 100_000;
+        let primes = primes_sieve_of_eratosthenes(limit);
+        let tested: Vec<usize> = (0..=limit).filter(|&n| is_prime_u64(n as u64)).collect();
+        assert_eq!(tested, primes);
+    }
+
+    #[test]
+    fn rejects_strong_pseudoprimes() {
+        // strong pseudoprimes to several small bases, and Carmichael numbers
+        for n in [2047u64, 1_373_653, 25_326_001, 3_215_031_751, 2_152_302_898_747, 3_474_749_660_383,
+            341_550_071_728_321, 3_825_123_056_546_413_051, 561, 1105, 1729] {
+            assert!(!is_prime_u64(n), "{} is composite", n);
+        }
+    }
+
+    #[test]
+    fn handles_the_top_of_the_u64_range() {
+        // the largest prime below 2^64
+        assert!(is_prime_u64(18_446_744_073_709_551_557));
+        assert!(!is_prime_u64(u64::MAX));
+        assert!(!is_prime_u64(18_446_744_073_709_551_559));
+        // (2^32 - 5)^2, the square of the largest prime below 2^32
+        assert!(!is_prime_u64(4_294_967_291 * 4_294_967_291));
+        assert!(is_prime_u64((1 << 61) - 1));
+    }
+
+    #[test]
+    fn mul_mod_u128_does_not_overflow() {
+        let m = u128::MAX - 2;
+        assert_eq!(mul_mod_u128(m - 1, m - 1, m), 1);
+        assert_eq!(mul_mod_u128(u128::MAX, 2, m), 4);
+        assert_eq!(mul_mod_u128(1 << 100, 1 << 100, (1 << 127) - 1), 1 << 73);
+    }
+
+    #[test]
+    fn probable_primes_beyond_u64() {
+        let mersenne_89: u128 = (1 << 89) - 1;
+        let mersenne_127: u128 = (1 << 127) - 1;
+        assert_eq!(primality(mersenne_89), Primality::ProbablyPrime);
+        assert_eq!(primality(mersenne_127), Primality::ProbablyPrime);
+        assert_eq!(primality(((1 << 61) - 1) * ((1 << 67) - 1)), Primality::NotPrime);
+        assert_eq!(primality(mersenne_89 * 3), Primality::NotPrime);
+        assert_eq!(primality(u128::MAX), Primality::NotPrime);
+        assert_eq!(primality(97), Primality::Prime);
+        assert_eq!(primality(1), Primality::NotPrime);
+        // the smallest strong pseudoprime to all twelve bases, which is why this is only a probable prime
+        assert_eq!(primality(318_665_857_834_031_151_167_461), Primality::ProbablyPrime);
+    }
+}
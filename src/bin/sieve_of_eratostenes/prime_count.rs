@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+
+use crate::prime_sieve::{PrimeCounter, PrimeSieve};
+use crate::segmented::for_each_prime_in_range;
+
+// below this, sieving directly is cheaper than setting up the Meissel-Lehmer machinery
+const DIRECT_SIEVE_LIMIT: u64 = 1 << 16;
+
+/// Beyond this the sieve up to x^(2/3) would take more than about 150 MB and counting some minutes;
+/// the command line does not count ranges with larger limits.
+pub const MAX_COUNT_LIMIT: u64 = 100_000_000_000_000;
+
+// φ(x, a) for a <= WHEEL_PRIMES is answered from a table over one period of the wheel 2·3·5·7·11·13
+const WHEEL_PRIMES: usize = 6;
+const WHEEL_SIZE: u64 = 2 * 3 * 5 * 7 * 11 * 13;
+
+// φ(x, a) results for x below this are memoized
+const PHI_CACHE_LIMIT: u64 = 1 << 16;
+
+fn icbrt(x: u64) -> u64 {
+    let mut root = (x as f64).cbrt() as u64;
+    while root > 0 && root.checked_pow(3).is_none_or(|cube| cube > x) {
+        root -= 1;
+    }
+    while (root + 1).checked_pow(3).is_some_and(|cube| cube <= x) {
+        root += 1;
+    }
+    root
+}
+
+/// Counts the primes <= x with Meissel's formula
+///
+///   π(x) = φ(x, a) + a - 1 - P2(x, a),   a = π(∛x)
+///   P2(x, a) = Σ_{a < i <= π(√x)} (π(x / p_i) - i + 1)
+///
+/// where φ(x, a) counts the numbers <= x that no prime among the first a primes divides.
+/// Every x / p_i above is below x^(2/3), so a bit-packed sieve up to x^(2/3) with a rank index
+/// answers all those π lookups; φ is expanded with Legendre's recursion
+/// φ(x, a) = φ(x, a - 1) - φ(x / p_a, a - 1), cut short by the wheel table and by π lookups.
+pub struct PrimeCounting {
+    counter: PrimeCounter,
+    // primes[i - 1] is the i-th prime p_i, up to √x
+    primes: Vec<u64>,
+    // wheel_phi[r] = φ(r, WHEEL_PRIMES) for r in 0..=WHEEL_SIZE
+    wheel_phi: Vec<u64>,
+    phi_cache: HashMap<(u64, usize), u64>,
+}
+
+impl PrimeCounting {
+    /// Prepares counting primes up to `max_x`: sieves up to max_x^(2/3).
+    pub fn new(max_x: u64) -> PrimeCounting {
+        let sieve_limit = (max_x / (icbrt(max_x) + 1)).max(max_x.isqrt()).max(WHEEL_SIZE);
+        let counter = PrimeCounter::new(PrimeSieve::new(sieve_limit));
+        let primes: Vec<u64> = counter.sieve().iter().take_while(|&p| p <= max_x.isqrt().max(13)).collect();
+
+        let mut wheel_phi: Vec<u64> = Vec::with_capacity(WHEEL_SIZE as usize + 1);
+        let mut coprime_count: u64 = 0;
+        for r in 0..=WHEEL_SIZE {
+            if r > 0 && primes[..WHEEL_PRIMES].iter().all(|&p| r % p != 0) {
+                coprime_count += 1;
+            }
+            wheel_phi.push(coprime_count);
+        }
+
+        PrimeCounting { counter, primes, wheel_phi, phi_cache: HashMap::new() }
+    }
+
+    fn pi_small(&self, x: u64) -> u64 {
+        self.counter.pi(x)
+    }
+
+    fn phi(&mut self, x: u64, a: usize) -> u64 {
+        if a == 0 || x == 0 {
+            return x;
+        }
+        if a == WHEEL_PRIMES {
+            return (x / WHEEL_SIZE) * self.wheel_phi[WHEEL_SIZE as usize] + self.wheel_phi[(x % WHEEL_SIZE) as usize];
+        }
+        if a < WHEEL_PRIMES {
+            return self.phi(x, a - 1) - self.phi(x / self.primes[a - 1], a - 1);
+        }
+        // only 1 and the primes p_(a+1), ..., up to x remain if x < p_(a+1)²
+        if let Some(&next_prime) = self.primes.get(a) {
+            if x < next_prime * next_prime && x <= self.counter.sieve().limit() {
+                return (self.pi_small(x) + 1).saturating_sub(a as u64).max(1);
+            }
+        }
+        if x < PHI_CACHE_LIMIT {
+            if let Some(&cached) = self.phi_cache.get(&(x, a)) {
+                return cached;
+            }
+        }
+
+        // φ(x, a) = φ(x, WHEEL_PRIMES) - Σ_{WHEEL_PRIMES < i <= a} φ(x / p_i, i - 1)
+        let mut result = self.phi(x, WHEEL_PRIMES);
+        for i in WHEEL_PRIMES + 1..=a {
+            let quotient = x / self.primes[i - 1];
+            if quotient == 0 {
+                break;
+            }
+            result -= self.phi(quotient, i - 1);
+        }
+
+        if x < PHI_CACHE_LIMIT {
+            self.phi_cache.insert((x, a), result);
+        }
+        result
+    }
+
+    /// π(x), the number of primes <= x; `x` must not exceed the `max_x` given to `new`.
+    pub fn pi(&mut self, x: u64) -> u64 {
+        if x <= self.counter.sieve().limit() {
+            return self.pi_small(x);
+        }
+        let a = self.pi_small(icbrt(x)) as usize;
+        let b = self.pi_small(x.isqrt()) as usize;
+
+        let mut p2: u64 = 0;
+        for i in a + 1..=b {
+            p2 += self.pi_small(x / self.primes[i - 1]) - (i as u64 - 1);
+        }
+        self.phi(x, a) + a as u64 - 1 - p2
+    }
+}
+
+/// π(x), the number of primes <= x.
+pub fn prime_pi(x: u64) -> u64 {
+    if x < DIRECT_SIEVE_LIMIT {
+        return PrimeSieve::new(x).count();
+    }
+    PrimeCounting::new(x).pi(x)
+}
+
+/// Number of primes p with lo <= p <= hi.
+pub fn count_primes_in_range(lo: u64, hi: u64) -> u64 {
+    if lo > hi {
+        return 0;
+    }
+    if hi < DIRECT_SIEVE_LIMIT {
+        return prime_pi(hi) - prime_pi(lo.saturating_sub(1));
+    }
+    // Meissel-Lehmer takes about hi^(2/3) steps for each count, sieving the range takes hi - lo
+    let cube_root = icbrt(hi);
+    if hi - lo < cube_root * cube_root {
+        let mut count: u64 = 0;
+        for_each_prime_in_range(lo, hi, |_| count += 1);
+        return count;
+    }
+    // one setup serves both counts
+    let mut counting = PrimeCounting::new(hi);
+    counting.pi(hi) - counting.pi(lo.saturating_sub(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primes_sieve_of_eratosthenes;
+
+    #[test]
+    fn matches_the_sieve_for_small_x() {
+        let primes: Vec<u64> = primes_sieve_of_eratosthenes(3000);
+        for x in 0..=3000u64 {
+            let expected = primes.iter().filter(|&&p| p <= x).count() as u64;
+            assert_eq!(prime_pi(x), expected, "π({})", x);
+        }
+    }
+
+    #[test]
+    fn matches_the_sieve_above_the_direct_sieve_limit() {
+        let mut counting = PrimeCounting::new(20_000_000);
+        let mut count: u64 = 0;
+        let mut checkpoints = (DIRECT_SIEVE_LIMIT..20_000_000).step_by(99_991).peekable();
+        for_each_prime_in_range(0, 20_000_000, |p| {
+            while let Some(&x) = checkpoints.peek() {
+                if x >= p {
+                    break;
+                }
+                assert_eq!(counting.pi(x), count, "π({})", x);
+                checkpoints.next();
+            }
+            count += 1;
+        });
+        assert_eq!(counting.pi(20_000_000), count);
+    }
+
+    #[test]
+    fn known_values() {
+        assert_eq!(prime_pi(1_000_000), 78_498);
+        assert_eq!(prime_pi(1_000_000_000), 50_847_534);
+        assert_eq!(prime_pi(10_000_000_000), 455_052_511);
+    }
+
+    #[test]
+    fn counts_ranges() {
+        assert_eq!(count_primes_in_range(10, 30), 6);
+        assert_eq!(count_primes_in_range(30, 10), 0);
+        assert_eq!(count_primes_in_range(0, 1), 0);
+        assert_eq!(count_primes_in_range(1_000_000, 2_000_000), 148_933 - 78_498);
+    }
+
+    #[test]
+    fn narrow_ranges_are_sieved() {
+        // just below, at and above the width where sieving takes over from Meissel-Lehmer
+        let hi = 10_000_000_000u64;
+        let width = icbrt(hi) * icbrt(hi);
+        let mut counting = PrimeCounting::new(hi);
+        let pi_hi = counting.pi(hi);
+        for lo in [hi - width - 1, hi - width, hi - width + 1, hi - 1_000, hi] {
+            assert_eq!(count_primes_in_range(lo, hi), pi_hi - counting.pi(lo - 1), "[{}, {}]", lo, hi);
+        }
+        // 10^12 - 11 is the largest prime below 10^12
+        assert_eq!(count_primes_in_range(999_999_999_000, 1_000_000_000_000), 38);
+        assert_eq!(count_primes_in_range(999_999_999_989, 999_999_999_989), 1);
+    }
+}
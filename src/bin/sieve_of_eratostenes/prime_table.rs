@@ -0,0 +1,389 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+// Binary layout, all fixed size integers little endian:
+//
+//   magic     4 bytes   "PRMS"
+//   version   u32       FORMAT_VERSION
+//   from      u64       start of the sieved range, inclusive
+//   limit     u64       end of the sieved range, inclusive
+//   gaps      varints   each prime minus the one before (the first one minus 0), LEB128 encoded
+//   count     u64       number of primes
+//   checksum  u64       FNV-1a over all preceding bytes
+//
+// Most gaps below 2^64 are smaller than 128 and take one byte, so a table up to 10^9
+// needs about 51 MB instead of 406 MB as u64 values.
+const MAGIC: &[u8; 4] = b"PRMS";
+const FORMAT_VERSION: u32 = 1;
+const HEADER_LEN: usize = 4 + 4 + 8 + 8;
+const TRAILER_LEN: usize = 8 + 8;
+
+// the first line of the text format is "# primes from <from> to <limit>, count <count>", followed by
+// one prime per line. The count is zero padded to a fixed width, so it can be filled in at the end.
+const TEXT_HEADER_PREFIX: &str = "# primes from ";
+const TEXT_COUNT_SEPARATOR: &str = ", count ";
+const TEXT_COUNT_WIDTH: usize = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableFormat {
+    Binary,
+    Text,
+}
+
+/// All primes of the range [from, limit], as written by `TableWriter` and read back by `load`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrimeTable {
+    pub from: u64,
+    pub limit: u64,
+    pub primes: Vec<u64>,
+}
+
+impl PrimeTable {
+    /// Whether the table holds all primes of [from, to].
+    pub fn covers(&self, from: u64, to: u64) -> bool {
+        from > to || (self.from <= from && to <= self.limit)
+    }
+
+    /// The primes of [from, to], which must be covered by the table.
+    pub fn primes_in_range(&self, from: u64, to: u64) -> &[u64] {
+        let start = self.primes.partition_point(|&p| p < from);
+        let end = self.primes.partition_point(|&p| p <= to);
+        &self.primes[start..end.max(start)]
+    }
+}
+
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Fnv1a {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+// returns the value and the number of bytes it took
+fn read_varint(bytes: &[u8]) -> Option<(u64, usize)> {
+    let mut value: u64 = 0;
+    for (i, &byte) in bytes.iter().enumerate().take(10) {
+        let bits = (byte & 0x7f) as u64;
+        if i == 9 && bits > 1 {
+            return None;
+        }
+        value |= bits << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+/// Writes primes to a file as they come in, to a temporary file first which is renamed on `finish`,
+/// so readers never see half a table. The temporary file is created fresh, never opened through an
+/// existing file or link, and removed again if the writer is dropped without finishing.
+pub struct TableWriter {
+    out: BufWriter<File>,
+    temporary: PathBuf,
+    finished: bool,
+    // where the count placeholder in the text header starts
+    count_offset: u64,
+    path: PathBuf,
+    format: TableFormat,
+    previous: u64,
+    count: u64,
+    checksum: Fnv1a,
+    buffer: Vec<u8>,
+}
+
+impl TableWriter {
+    pub fn create(path: &Path, format: TableFormat, from: u64, limit: u64) -> io::Result<TableWriter> {
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(format!(".{}.tmp", process::id()));
+        let temporary = PathBuf::from(temporary);
+        let file = OpenOptions::new().write(true).create_new(true).open(&temporary)?;
+        let mut writer = TableWriter {
+            out: BufWriter::new(file),
+            temporary,
+            finished: false,
+            count_offset: 0,
+            path: path.to_path_buf(),
+            format,
+            previous: 0,
+            count: 0,
+            checksum: Fnv1a::new(),
+            buffer: Vec::new(),
+        };
+        match format {
+            TableFormat::Binary => {
+                writer.buffer.extend_from_slice(MAGIC);
+                writer.buffer.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+                writer.buffer.extend_from_slice(&from.to_le_bytes());
+                writer.buffer.extend_from_slice(&limit.to_le_bytes());
+                writer.flush_buffer()?;
+            }
+            TableFormat::Text => {
+                let range = format!("{}{} to {}{}", TEXT_HEADER_PREFIX, from, limit, TEXT_COUNT_SEPARATOR);
+                writer.count_offset = range.len() as u64;
+                writeln!(writer.out, "{}{:0width$}", range, 0, width = TEXT_COUNT_WIDTH)?;
+            }
+        }
+        Ok(writer)
+    }
+
+    fn flush_buffer(&mut self) -> io::Result<()> {
+        self.checksum.update(&self.buffer);
+        self.out.write_all(&self.buffer)?;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    /// Primes must come in ascending order.
+    pub fn write(&mut self, prime: u64) -> io::Result<()> {
+        match self.format {
+            TableFormat::Binary => {
+                write_varint(&mut self.buffer, prime - self.previous);
+                if self.buffer.len() >= 1 << 16 {
+                    self.flush_buffer()?;
+                }
+            }
+            TableFormat::Text => writeln!(self.out, "{}", prime)?,
+        }
+        self.previous = prime;
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        match self.format {
+            TableFormat::Binary => {
+                self.buffer.extend_from_slice(&self.count.to_le_bytes());
+                self.flush_buffer()?;
+                let checksum = self.checksum.0;
+                self.out.write_all(&checksum.to_le_bytes())?;
+                self.out.flush()?;
+            }
+            TableFormat::Text => {
+                self.out.flush()?;
+                let file = self.out.get_mut();
+                file.seek(SeekFrom::Start(self.count_offset))?;
+                write!(file, "{:0width$}", self.count, width = TEXT_COUNT_WIDTH)?;
+            }
+        }
+        fs::rename(&self.temporary, &self.path)?;
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl Drop for TableWriter {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = fs::remove_file(&self.temporary);
+        }
+    }
+}
+
+/// Reads a table in either format, telling them apart by the magic bytes of the binary format.
+pub fn load(path: &Path) -> io::Result<PrimeTable> {
+    let mut reader = BufReader::new(File::open(path)?);
+    if reader.fill_buf()?.starts_with(MAGIC) {
+        let mut bytes: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        decode_binary(&bytes)
+    } else {
+        read_text(reader)
+    }
+}
+
+fn check_primes(from: u64, limit: u64, primes: &[u64]) -> io::Result<()> {
+    if primes.windows(2).any(|pair| pair[0] >= pair[1]) {
+        return Err(invalid_data("the primes of the table are not in ascending order"));
+    }
+    match (primes.first(), primes.last()) {
+        (Some(&first), Some(&last)) if first < from || last > limit => {
+            Err(invalid_data("the table holds primes outside of its range"))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn decode_binary(bytes: &[u8]) -> io::Result<PrimeTable> {
+    if bytes.len() < HEADER_LEN + TRAILER_LEN || &bytes[0..4] != MAGIC {
+        return Err(invalid_data("not a prime table file"));
+    }
+    let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+    if version != FORMAT_VERSION {
+        return Err(invalid_data(&format!("unsupported prime table format version {}", version)));
+    }
+    let (content, checksum) = bytes.split_at(bytes.len() - 8);
+    let mut hash = Fnv1a::new();
+    hash.update(content);
+    if hash.0 != u64::from_le_bytes(checksum.try_into().unwrap()) {
+        return Err(invalid_data("prime table checksum mismatch"));
+    }
+    let from = u64::from_le_bytes(content[8..16].try_into().unwrap());
+    let limit = u64::from_le_bytes(content[16..24].try_into().unwrap());
+    let (mut gaps, count) = content[HEADER_LEN..].split_at(content.len() - HEADER_LEN - 8);
+    let count = u64::from_le_bytes(count.try_into().unwrap());
+
+    let mut primes: Vec<u64> = Vec::new();
+    let mut previous: u64 = 0;
+    while !gaps.is_empty() {
+        let (gap, length) = read_varint(gaps).ok_or_else(|| invalid_data("malformed gap in the prime table"))?;
+        previous = previous.checked_add(gap).ok_or_else(|| invalid_data("prime table exceeds the u64 range"))?;
+        primes.push(previous);
+        gaps = &gaps[length..];
+    }
+    if primes.len() as u64 != count {
+        return Err(invalid_data("prime table length does not match its prime count"));
+    }
+    check_primes(from, limit, &primes)?;
+    Ok(PrimeTable { from, limit, primes })
+}
+
+fn read_text(reader: impl BufRead) -> io::Result<PrimeTable> {
+    let mut lines = reader.lines();
+    let header = lines.next().transpose()?.unwrap_or_default();
+    let (from, limit, count) = header.strip_prefix(TEXT_HEADER_PREFIX)
+        .and_then(|rest| rest.split_once(TEXT_COUNT_SEPARATOR))
+        .and_then(|(range, count)| Some((range.split_once(" to ")?, count.parse::<u64>().ok()?)))
+        .and_then(|((from, limit), count)| Some((from.parse::<u64>().ok()?, limit.parse::<u64>().ok()?, count)))
+        .ok_or_else(|| invalid_data("not a prime table file"))?;
+
+    let mut primes: Vec<u64> = Vec::new();
+    for line in lines {
+        let line = line?;
+        let prime = line.trim().parse::<u64>()
+            .map_err(|_| invalid_data(&format!("'{}' in the prime table is not a number", line)))?;
+        primes.push(prime);
+    }
+    if primes.len() as u64 != count {
+        return Err(invalid_data("prime table length does not match its prime count"));
+    }
+    check_primes(from, limit, &primes)?;
+    Ok(PrimeTable { from, limit, primes })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::segmented::primes_in_range;
+
+    fn round_trip(name: &str, format: TableFormat, from: u64, limit: u64) -> PrimeTable {
+        let path = env::temp_dir().join(format!("sieve_prime_table_test_{}_{}", std::process::id(), name));
+        let mut writer = TableWriter::create(&path, format, from, limit).unwrap();
+        for p in primes_in_range(from, limit) {
+            writer.write(p).unwrap();
+        }
+        writer.finish().unwrap();
+        let table = load(&path);
+        fs::remove_file(&path).unwrap();
+        table.unwrap()
+    }
+
+    #[test]
+    fn binary_and_text_round_trip() {
+        for format in [TableFormat::Binary, TableFormat::Text] {
+            for (from, limit) in [(0, 0), (0, 2), (0, 1_000_000), (999_999_000_000, 1_000_000_000_000)] {
+                let table = round_trip(&format!("{:?}_{}", format, limit), format, from, limit);
+                assert_eq!(table, PrimeTable { from, limit, primes: primes_in_range(from, limit) });
+            }
+        }
+    }
+
+    #[test]
+    fn varints() {
+        for value in [0, 1, 127, 128, 300, 1 << 35, u64::MAX] {
+            let mut bytes: Vec<u8> = Vec::new();
+            write_varint(&mut bytes, value);
+            assert_eq!(read_varint(&bytes), Some((value, bytes.len())));
+        }
+        assert_eq!(read_varint(&[0x80, 0x80]), None);
+    }
+
+    #[test]
+    fn rejects_corrupted_files() {
+        let path = env::temp_dir().join(format!("sieve_prime_table_test_{}_corrupted", std::process::id()));
+        let mut writer = TableWriter::create(&path, TableFormat::Binary, 0, 1000).unwrap();
+        for p in primes_in_range(0, 1000) {
+            writer.write(p).unwrap();
+        }
+        writer.finish().unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(decode_binary(&bytes).is_ok());
+        bytes[HEADER_LEN + 3] ^= 1;
+        assert_eq!(decode_binary(&bytes).unwrap_err().to_string(), "prime table checksum mismatch");
+        assert!(decode_binary(&bytes[..10]).is_err());
+        assert!(read_text("2\n3\n".as_bytes()).is_err());
+        assert!(read_text("# primes from 0 to 10, count 3\n2\n5\n3\n".as_bytes()).is_err());
+        assert!(read_text("# primes from 0 to 10, count 2\n2\n13\n".as_bytes()).is_err());
+        // the header of the first version had no count
+        assert!(read_text("# primes from 0 to 10\n2\n3\n5\n7\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_text_tables() {
+        let path = env::temp_dir().join(format!("sieve_prime_table_test_{}_truncated", std::process::id()));
+        let mut writer = TableWriter::create(&path, TableFormat::Text, 0, 100).unwrap();
+        for p in primes_in_range(0, 100) {
+            writer.write(p).unwrap();
+        }
+        writer.finish().unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(text.starts_with("# primes from 0 to 100, count 00000000000000000025\n2\n3\n"));
+        assert_eq!(read_text(text.as_bytes()).unwrap().primes.len(), 25);
+        let truncated = &text[..text.len() - "97\n".len()];
+        assert_eq!(read_text(truncated.as_bytes()).unwrap_err().to_string(),
+                   "prime table length does not match its prime count");
+    }
+
+    #[test]
+    fn unfinished_writers_leave_no_temporary_file() {
+        let path = env::temp_dir().join(format!("sieve_prime_table_test_{}_unfinished", std::process::id()));
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(format!(".{}.tmp", std::process::id()));
+        let mut writer = TableWriter::create(&path, TableFormat::Binary, 0, 100).unwrap();
+        writer.write(2).unwrap();
+        assert!(Path::new(&temporary).exists());
+        // another writer for the same file does not open the temporary file
+        assert_eq!(TableWriter::create(&path, TableFormat::Binary, 0, 100).err().map(|e| e.kind()),
+                   Some(ErrorKind::AlreadyExists));
+        drop(writer);
+        assert!(!Path::new(&temporary).exists());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn selects_primes_in_range() {
+        let table = PrimeTable { from: 0, limit: 30, primes: vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29] };
+        assert_eq!(table.primes_in_range(4, 12), &[5, 7, 11]);
+        assert_eq!(table.primes_in_range(12, 4), &[] as &[u64]);
+        assert!(table.covers(0, 30));
+        assert!(!table.covers(0, 31));
+    }
+}
@@ -0,0 +1,109 @@
+// an iterator for code which does not know its upper limit in advance; the command line always
+// has one and sieves through the segmented module, so in the binary only the tests use this
+#![cfg_attr(not(test), allow(dead_code))]
+
+use crate::segmented::{base_primes, sieve_segment, SEGMENT_SIZE};
+
+/// All primes in ascending order, without an upper limit to be chosen in advance.
+///
+/// `primes().nth(1_000_000)` or `primes().take_while(|&p| p < 1000)` sieve just as far as needed.
+pub fn primes() -> Primes {
+    Primes::starting_at(0)
+}
+
+/// Sieves one segment after the other on demand, extending the base primes as the segments move up.
+pub struct Primes {
+    segment: Vec<bool>,
+    // the number at segment[0]
+    low: u64,
+    // index of the next candidate within the segment
+    position: usize,
+    base_primes: Vec<u64>,
+    // the base primes cover all primes up to here
+    base_limit: u64,
+    // set once the segment ending at u64::MAX has been sieved
+    last_segment: bool,
+}
+
+impl Primes {
+    /// All primes >= `start`.
+    pub fn starting_at(start: u64) -> Primes {
+        let mut primes = Primes {
+            segment: vec![false; SEGMENT_SIZE],
+            low: start,
+            position: 0,
+            base_primes: Vec::new(),
+            base_limit: 0,
+            last_segment: false,
+        };
+        primes.sieve_current_segment();
+        primes
+    }
+
+    fn sieve_current_segment(&mut self) {
+        let high = self.low.saturating_add(SEGMENT_SIZE as u64 - 1);
+        self.last_segment = high == u64::MAX;
+        let needed = high.isqrt();
+        if needed > self.base_limit {
+            // grow geometrically, so the base primes are not recomputed for every segment
+            self.base_limit = needed.saturating_mul(2).min(u32::MAX as u64);
+            self.base_primes = base_primes(self.base_limit);
+        }
+        let length = (high - self.low) as usize + 1;
+        self.segment.truncate(length);
+        sieve_segment(&mut self.segment, self.low, &self.base_primes);
+        self.position = 0;
+    }
+}
+
+impl Iterator for Primes {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        loop {
+            while self.position < self.segment.len() {
+                let i = self.position;
+                self.position += 1;
+                if self.segment[i] {
+                    return Some(self.low + i as u64);
+                }
+            }
+            if self.last_segment {
+                return None;
+            }
+            self.low += SEGMENT_SIZE as u64;
+            self.sieve_current_segment();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primality::is_prime_u64;
+    use crate::primes_sieve_of_eratosthenes;
+
+    #[test]
+    fn agrees_with_the_classic_sieve() {
+        let limit = 3_000_000;
+        let expected: Vec<u64> = primes_sieve_of_eratosthenes(limit);
+        let lazy: Vec<u64> = primes().take_while(|&p| p <= limit).collect();
+        assert_eq!(lazy, expected);
+    }
+
+    #[test]
+    fn nth_prime() {
+        assert_eq!(primes().next(), Some(2));
+        assert_eq!(primes().nth(9), Some(29));
+        // the 1,000,001st prime, nth counts from 0
+        assert_eq!(primes().nth(1_000_000), Some(15_485_867));
+    }
+
+    #[test]
+    fn starting_at_a_large_number() {
+        let start = 1_000_000_000_000u64;
+        let found: Vec<u64> = Primes::starting_at(start).take(20).collect();
+        let expected: Vec<u64> = (start..).filter(|&n| is_prime_u64(n)).take(20).collect();
+        assert_eq!(found, expected);
+    }
+}
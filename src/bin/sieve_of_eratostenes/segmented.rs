@@ -0,0 +1,222 @@
+use std::ops::ControlFlow;
+
+use crate::primes_sieve_of_eratosthenes;
+
+// one byte per number, so a segment fits comfortably into a typical L2 cache
+pub const SEGMENT_SIZE: usize = 1 << 18;
+
+/// Sieving primes up to √hi, which are needed to sieve the segments of [lo, hi].
+///
+/// Small limits go through the classic sieve; larger ones are sieved segment-wise themselves,
+/// so the memory stays bounded by the segment size and the number of base primes.
+pub fn base_primes(limit: u64) -> Vec<u64> {
+    if limit < SEGMENT_SIZE as u64 {
+        primes_sieve_of_eratosthenes(limit)
+    } else {
+        primes_in_range(0, limit)
+    }
+}
+
+/// Marks the composites of the segment [low, low + segment.len()) using the given base primes,
+/// which must cover √(low + segment.len() - 1). `segment[i]` stays true iff `low + i` is prime.
+pub fn sieve_segment(segment: &mut [bool], low: u64, base_primes: &[u64]) {
+    segment.fill(true);
+    let high = low + (segment.len() as u64 - 1);
+    // 0 and 1 are not prime
+    for i in low..=high.min(1) {
+        segment[(i - low) as usize] = false;
+    }
+
+    for &p in base_primes {
+        let p_squared = match p.checked_mul(p) {
+            Some(p_squared) if p_squared <= high => p_squared,
+            _ => break,
+        };
+        // smaller multiples of p have a smaller prime factor and are marked by that one
+        let first_multiple = match low.div_ceil(p).checked_mul(p) {
+            Some(multiple) => multiple.max(p_squared),
+            None => continue,
+        };
+        let mut i = (first_multiple - low) as usize;
+        while i < segment.len() {
+            segment[i] = false;
+            i += p as usize;
+        }
+    }
+}
+
+/// Calls `f` with every prime in [lo, hi] (both inclusive) in ascending order,
+/// sieving one segment of `SEGMENT_SIZE` numbers at a time.
+pub fn for_each_prime_in_range(lo: u64, hi: u64, mut f: impl FnMut(u64)) {
+    let _: ControlFlow<()> = try_for_each_prime_in_range(lo, hi, |p| {
+        f(p);
+        ControlFlow::Continue(())
+    });
+}
+
+/// Like `for_each_prime_in_range`, but stops as soon as `f` returns `ControlFlow::Break`.
+pub fn try_for_each_prime_in_range<B>(lo: u64, hi: u64, f: impl FnMut(u64) -> ControlFlow<B>) -> ControlFlow<B> {
+    if lo > hi {
+        return ControlFlow::Continue(());
+    }
+    sieve_range(lo, hi, &base_primes(hi.isqrt()), f)
+}
+
+/// Like `try_for_each_prime_in_range`, with base primes covering √hi given by the caller,
+/// so several ranges can share them.
+pub fn sieve_range<B>(lo: u64, hi: u64, base_primes: &[u64], mut f: impl FnMut(u64) -> ControlFlow<B>) -> ControlFlow<B> {
+    if lo > hi {
+        return ControlFlow::Continue(());
+    }
+    // hi - lo + 1 would overflow for the whole u64 range
+    let mut segment: Vec<bool> = vec![true; (hi - lo).min(SEGMENT_SIZE as u64 - 1) as usize + 1];
+
+    let mut low = lo;
+    loop {
+        let high = hi.min(low.saturating_add(SEGMENT_SIZE as u64 - 1));
+        let segment = &mut segment[..(high - low) as usize + 1];
+        sieve_segment(segment, low, base_primes);
+        for (i, &is_prime) in segment.iter().enumerate() {
+            if is_prime {
+                f(low + i as u64)?;
+            }
+        }
+        if high == hi {
+            return ControlFlow::Continue(());
+        }
+        low = high + 1;
+    }
+}
+
+/// The largest prime in [lo, hi], sieving one segment after the other downwards from hi.
+pub fn last_prime_in_range(lo: u64, hi: u64) -> Option<u64> {
+    if lo > hi {
+        return None;
+    }
+    let base_primes = base_primes(hi.isqrt());
+    let mut segment: Vec<bool> = vec![true; (hi - lo).min(SEGMENT_SIZE as u64 - 1) as usize + 1];
+
+    let mut high = hi;
+    loop {
+        let low = lo.max(high - (high - lo).min(SEGMENT_SIZE as u64 - 1));
+        let segment = &mut segment[..(high - low) as usize + 1];
+        sieve_segment(segment, low, &base_primes);
+        if let Some(i) = segment.iter().rposition(|&is_prime| is_prime) {
+            return Some(low + i as u64);
+        }
+        if low == lo {
+            return None;
+        }
+        high = low - 1;
+    }
+}
+
+/// All primes in [lo, hi] (both inclusive).
+pub fn primes_in_range(lo: u64, hi: u64) -> Vec<u64> {
+    let mut primes: Vec<u64> = Vec::new();
+    for_each_prime_in_range(lo, hi, |p| primes.push(p));
+    primes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primality::is_prime_u64;
+
+    fn classic(lo: u64, hi: u64) -> Vec<u64> {
+        primes_sieve_of_eratosthenes(hi).into_iter().filter(|&p| p >= lo).collect()
+    }
+
+    #[test]
+    fn base_primes_around_the_segment_size() {
+        let segment = SEGMENT_SIZE as u64;
+        for limit in [0, 1, 2, 3, segment - 1, segment, segment + 1, 3 * segment + 7] {
+            assert_eq!(base_primes(limit), primes_sieve_of_eratosthenes(limit), "limit {}", limit);
+        }
+    }
+
+    #[test]
+    fn ranges_around_segment_boundaries() {
+        let segment = SEGMENT_SIZE as u64;
+        for (lo, hi) in [(0, segment - 1), (0, segment), (0, segment + 1), (1, segment + 1),
+                         (segment - 1, segment), (segment, segment + 1), (segment - 10, 2 * segment + 10)] {
+            assert_eq!(primes_in_range(lo, hi), classic(lo, hi), "[{}, {}]", lo, hi);
+        }
+    }
+
+    #[test]
+    fn tiny_and_empty_ranges() {
+        assert_eq!(primes_in_range(0, 0), Vec::<u64>::new());
+        assert_eq!(primes_in_range(0, 1), Vec::<u64>::new());
+        assert_eq!(primes_in_range(1, 1), Vec::<u64>::new());
+        assert_eq!(primes_in_range(0, 2), vec![2]);
+        assert_eq!(primes_in_range(1, 3), vec![2, 3]);
+        assert_eq!(primes_in_range(97, 97), vec![97]);
+        assert_eq!(primes_in_range(98, 98), Vec::<u64>::new());
+        assert_eq!(primes_in_range(10, 9), Vec::<u64>::new());
+        assert_eq!(primes_in_range(u64::MAX, 0), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn last_prime() {
+        let segment = SEGMENT_SIZE as u64;
+        for (lo, hi) in [(0, 0), (0, 1), (0, 2), (3, 3), (24, 28), (0, 100), (segment - 100, segment + 1),
+                         (0, 3 * segment + 5), (1_000_000_000, 1_000_000_000 + segment)] {
+            assert_eq!(last_prime_in_range(lo, hi), primes_in_range(lo, hi).last().copied(), "[{}, {}]", lo, hi);
+        }
+        assert_eq!(last_prime_in_range(10, 9), None);
+        // no prime among the 153 numbers after 4652353, so the search has to cross a segment boundary
+        assert_eq!(last_prime_in_range(0, 4_652_506), Some(4_652_353));
+        assert_eq!(last_prime_in_range(4_652_354, 4_652_506), None);
+        assert_eq!(last_prime_in_range(0, 1_000_000_000_000), Some(999_999_999_989));
+    }
+
+    #[test]
+    fn sieve_segment_marks_0_and_1() {
+        let base = primes_sieve_of_eratosthenes(10);
+        let mut segment = vec![true; 12];
+        sieve_segment(&mut segment, 0, &base);
+        let primes: Vec<usize> = (0..segment.len()).filter(|&i| segment[i]).collect();
+        assert_eq!(primes, vec![2, 3, 5, 7, 11]);
+        sieve_segment(&mut segment[..1], 1, &base);
+        assert!(!segment[0]);
+        // p itself stays, only its multiples from p² on are crossed out
+        let mut segment = vec![true; 5];
+        sieve_segment(&mut segment, 5, &base);
+        assert_eq!(segment, vec![true, false, true, false, false]);
+    }
+
+    #[test]
+    fn try_for_each_stops_on_break() {
+        let mut seen: Vec<u64> = Vec::new();
+        let result = try_for_each_prime_in_range(0, 1 << 40, |p| {
+            seen.push(p);
+            if p > 20 { ControlFlow::Break(p) } else { ControlFlow::Continue(()) }
+        });
+        assert_eq!(result, ControlFlow::Break(23));
+        assert_eq!(seen, vec![2, 3, 5, 7, 11, 13, 17, 19, 23]);
+        // the whole u64 range, without base primes every number from 2 on survives
+        assert_eq!(sieve_range(0, u64::MAX, &[], ControlFlow::Break), ControlFlow::Break(2));
+    }
+
+    #[test]
+    fn range_ending_at_u64_max() {
+        // all base primes up to 2^32 would take too long, so only the small ones are given:
+        // what is left are exactly the numbers without a small factor, the primes among them included
+        let base = primes_sieve_of_eratosthenes(1_000u64);
+        let lo = u64::MAX - SEGMENT_SIZE as u64 - 100;
+        let mut survivors: Vec<u64> = Vec::new();
+        let _: ControlFlow<()> = sieve_range(lo, u64::MAX, &base, |n| {
+            survivors.push(n);
+            ControlFlow::Continue(())
+        });
+        let expected: Vec<u64> = (lo..=u64::MAX)
+            .filter(|&n| base.iter().all(|&p| !n.is_multiple_of(p)))
+            .collect();
+        assert_eq!(survivors, expected);
+        let primes: Vec<u64> = survivors.into_iter().filter(|&n| is_prime_u64(n)).collect();
+        // the largest prime below 2^64
+        assert_eq!(primes.last(), Some(&18_446_744_073_709_551_557));
+        assert_eq!(primes, (lo..=u64::MAX).filter(|&n| is_prime_u64(n)).collect::<Vec<u64>>());
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::{BTreeMap, VecDeque};
+
+// the races of the Chebyshev bias: primes ≡ m-1 (mod m) against primes ≡ 1 (mod m)
+const RACE_MODULI: [u64; 2] = [3, 4];
+
+/// How the primes of one residue class race against another one, as in Chebyshev's bias.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResidueRace {
+    pub modulus: u64,
+    /// number of primes per residue, indexed by the residue
+    pub counts: Vec<u64>,
+    /// after how many of the primes the class m-1 was strictly ahead of the class 1
+    pub leads: u64,
+}
+
+/// Gap and constellation statistics, collected one prime at a time in ascending order,
+/// so they can be fed from a segmented sieve without keeping the primes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrimeStats {
+    pub count: u64,
+    /// gaps larger than all gaps before them, with the prime they follow
+    pub maximal_gaps: Vec<(u64, u64)>,
+    /// every gap size that occurred, with the first prime it follows
+    pub first_gaps: BTreeMap<u64, u64>,
+    /// pairs (p, p + 2)
+    pub twins: u64,
+    /// pairs (p, p + 4)
+    pub cousins: u64,
+    /// pairs (p, p + 6)
+    pub sexy: u64,
+    /// consecutive primes p, ..., p + 6
+    pub triplets: u64,
+    /// primes p, p + 2, p + 6, p + 8
+    pub quadruplets: u64,
+    /// consecutive primes p, ..., p + 12
+    pub quintuplets: u64,
+    pub races: Vec<ResidueRace>,
+    // the last primes seen, enough to look back 12 for the quintuplets
+    recent: VecDeque<u64>,
+}
+
+impl PrimeStats {
+    pub fn new() -> PrimeStats {
+        PrimeStats {
+            count: 0,
+            maximal_gaps: Vec::new(),
+            first_gaps: BTreeMap::new(),
+            twins: 0,
+            cousins: 0,
+            sexy: 0,
+            triplets: 0,
+            quadruplets: 0,
+            quintuplets: 0,
+            races: RACE_MODULI.iter()
+                .map(|&modulus| ResidueRace { modulus, counts: vec![0; modulus as usize], leads: 0 })
+                .collect(),
+            recent: VecDeque::with_capacity(4),
+        }
+    }
+
+    /// Primes must come in ascending order.
+    pub fn add(&mut self, p: u64) {
+        self.count += 1;
+
+        if let Some(&previous) = self.recent.back() {
+            let gap = p - previous;
+            self.first_gaps.entry(gap).or_insert(previous);
+            if self.maximal_gaps.last().is_none_or(|&(record, _)| gap > record) {
+                self.maximal_gaps.push((gap, previous));
+            }
+        }
+
+        for &q in self.recent.iter() {
+            match p - q {
+                2 => self.twins += 1,
+                4 => self.cousins += 1,
+                6 => self.sexy += 1,
+                _ => {}
+            }
+        }
+        // the narrowest admissible constellations of 3, 4 and 5 primes
+        let n = self.recent.len();
+        if n >= 2 && p - self.recent[n - 2] == 6 {
+            self.triplets += 1;
+        }
+        // (3, 5, 7, 11) has the same width, but does not fit the pattern p, p+2, p+6, p+8
+        if n >= 3 && p - self.recent[n - 3] == 8 && self.recent[n - 1] - self.recent[n - 3] == 6 {
+            self.quadruplets += 1;
+        }
+        if n >= 4 && p - self.recent[n - 4] == 12 {
+            self.quintuplets += 1;
+        }
+
+        for race in self.races.iter_mut() {
+            let modulus = race.modulus as usize;
+            race.counts[(p % race.modulus) as usize] += 1;
+            if race.counts[modulus - 1] > race.counts[1] {
+                race.leads += 1;
+            }
+        }
+
+        if self.recent.len() == 4 {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(p);
+    }
+}
+
+impl Default for PrimeStats {
+    fn default() -> Self {
+        PrimeStats::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segmented::primes_in_range;
+
+    fn stats(from: u64, to: u64) -> PrimeStats {
+        let mut stats = PrimeStats::new();
+        for p in primes_in_range(from, to) {
+            stats.add(p);
+        }
+        stats
+    }
+
+    #[test]
+    fn small_range() {
+        // 2 3 5 7 11 13 17 19 23 29 31 37 41 43 47
+        let stats = stats(0, 50);
+        assert_eq!(stats.count, 15);
+        assert_eq!(stats.maximal_gaps, vec![(1, 2), (2, 3), (4, 7), (6, 23)]);
+        assert_eq!(stats.first_gaps.into_iter().collect::<Vec<_>>(), vec![(1, 2), (2, 3), (4, 7), (6, 23)]);
+        assert_eq!(stats.twins, 6); // 3 5 11 17 29 41
+        assert_eq!(stats.cousins, 6); // 3 7 13 19 37 43
+        assert_eq!(stats.sexy, 9); // 5 7 11 13 17 23 31 37 41
+        assert_eq!(stats.triplets, 7); // 5 7 11 13 17 37 41
+        assert_eq!(stats.quadruplets, 2); // 5 11
+        assert_eq!(stats.quintuplets, 3); // 5 7 11
+        assert_eq!(stats.races[1].counts, vec![0, 6, 1, 8]);
+    }
+
+    #[test]
+    fn known_values_up_to_10_pow_6() {
+        let stats = stats(0, 1_000_000);
+        assert_eq!(stats.count, 78_498);
+        assert_eq!(stats.twins, 8_169);
+        assert_eq!(stats.maximal_gaps.last(), Some(&(114, 492_113)));
+        assert_eq!(stats.first_gaps[&100], 396_733);
+        // the bias: primes ≡ 3 (mod 4) are ahead almost all the time
+        let race = &stats.races[1];
+        assert_eq!(race.counts[1] + race.counts[3] + 1, stats.count);
+        assert!(race.leads > stats.count * 99 / 100);
+    }
+}
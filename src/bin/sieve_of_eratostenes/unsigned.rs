@@ -0,0 +1,31 @@
+use std::fmt::Debug;
+
+/// The unsigned integer types a prime list can be made of, so callers can pick the smallest one
+/// that holds their limit: a `Vec<u32>` of primes takes half the memory of a `Vec<u64>`.
+///
+/// The sieve itself works on `usize` indexes, these are the conversions in and out.
+pub trait Unsigned: Copy + PartialOrd + Debug {
+    /// Panics if the value does not fit into usize, which can only happen on 32 bit platforms.
+    fn to_usize(self) -> usize;
+
+    /// Panics if n does not fit into the type.
+    fn from_usize(n: usize) -> Self;
+}
+
+macro_rules! impl_unsigned {
+    ($($t:ty),*) => {
+        $(
+            impl Unsigned for $t {
+                fn to_usize(self) -> usize {
+                    usize::try_from(self).expect("the value does not fit into usize")
+                }
+
+                fn from_usize(n: usize) -> Self {
+                    <$t>::try_from(n).expect(concat!("the value does not fit into ", stringify!($t)))
+                }
+            }
+        )*
+    };
+}
+
+impl_unsigned!(u8, u16, u32, u64, usize);